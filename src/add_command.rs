@@ -11,21 +11,29 @@
 // OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
 // PERFORMANCE OF THIS SOFTWARE.
 
-use std::iter::zip;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
 use argh::FromArgs;
-use atom_syndication::{Content, EntryBuilder, Feed, Person};
+use atom_syndication::{
+    Category, Content, Entry as AtomEntry, EntryBuilder, Feed, Link as AtomLink, Person, Text,
+};
 use chrono::{DateTime, Utc};
-use log::error;
+use log::{debug, warn};
 
+use crate::content_type::{base64_encode, check_content_type, is_base64_type, is_mime_type};
 use crate::kaboom_command::KaboomCommand;
 use crate::kaboom_feed::KaboomFeed;
+use crate::stringable_category::StringableCategory;
+use crate::stringable_link::StringableLink;
+use crate::stringable_person::StringablePerson;
+use crate::text_construct::{xhtml_div, TextKind};
 
 #[derive(FromArgs, Debug)]
 #[argh(subcommand, name = "add")]
-/// Add entries to the feed. If *content* is supplied, its source is assumed to
-/// be the same URI as *id*.
+/// Add entries to the feed. By default, refuses to add an entry whose *id*
+/// already exists in the feed; see *replace* and *upsert*.
 pub struct AddCommand {
     #[argh(positional)]
     /// the URI of the entry
@@ -35,33 +43,91 @@ pub struct AddCommand {
     /// the title of the entry
     title: String,
 
+    #[argh(option)]
+    /// the type of *title*: text (the default), html, or xhtml
+    title_type: Option<TextKind>,
+
     #[argh(option, short = 's')]
     /// a short summary of the entry
     summary: Option<String>,
 
+    #[argh(option, short = 'S')]
+    /// path to a file containing a short summary of the entry, or - to read it
+    /// from standard input. mutually exclusive with *summary*.
+    summary_file: Option<PathBuf>,
+
+    #[argh(option)]
+    /// the type of *summary*: text (the default), html, or xhtml
+    summary_type: Option<TextKind>,
+
     #[argh(option, short = 'c')]
     /// the full content of the entry
     content: Option<String>,
 
+    #[argh(option, short = 'C')]
+    /// path to a file containing the full content of the entry, or - to read
+    /// it from standard input. mutually exclusive with *content*.
+    content_file: Option<PathBuf>,
+
+    #[argh(option)]
+    /// a URL where the full content of the entry can be found, for content
+    /// that isn't embedded in the feed (a podcast episode, say). requires a
+    /// MIME type as *content_type*, and a *summary*. mutually exclusive with
+    /// *content* and *content_file*.
+    content_src: Option<String>,
+
     #[argh(option, short = 'T')]
     /// the content type of *content*; must be "text", "html", "xhtml", or a
-    /// MIME type. ignored if *content* is not provided.
+    /// MIME type, or "markdown" if kaboom was built with Markdown support, in
+    /// which case the content is stored as rendered HTML. content of a binary
+    /// MIME type is base64-encoded, and XML MIME types need *content_src*.
+    /// ignored if neither *content* nor *content_src* is provided. if omitted,
+    /// inferred from the extension of *content_file* where possible.
     content_type: Option<String>,
 
+    #[argh(switch, short = 'P')]
+    /// if no *summary* is provided, use the first paragraph of *content* as the
+    /// summary. only supported for Markdown content.
+    summary_from_content: bool,
+
     #[argh(option, short = 'L')]
     /// the language of *content*, often a code like en-us. ignored if *content*
     /// is not provided
     content_language: Option<String>,
 
     #[argh(option, short = 'a')]
-    /// name(s) of authors associated with this entry. can be specified multiple
-    /// times, must have the same number of names and emails.
-    author_names: Vec<String>,
+    /// an author of this entry, in the form "Name <email> (uri)", where the
+    /// email and uri are optional. can be specified multiple times. if omitted,
+    /// the feed's own authors are used.
+    author: Vec<StringablePerson>,
+
+    #[argh(option, short = 'l')]
+    /// a link related to this entry, in the same form as `kaboom meta
+    /// --rel-link`, for example an enclosure: https://example.com/001.mp3
+    /// [rel=enclosure][type=audio/mpeg][length=12345]. can be specified
+    /// multiple times. unless one of these is rel=alternate, an http(s) *id*
+    /// is itself used as the entry's rel=alternate link.
+    link: Vec<StringableLink>,
+
+    #[argh(option, short = 't')]
+    /// a category (tag) of this entry, in the form
+    /// "term[scheme=URI][label=Human Label]", where both suffixes are
+    /// optional. can be specified multiple times.
+    category: Vec<StringableCategory>,
 
-    #[argh(option, short = 'A')]
-    /// email address(es) of authors associated with this entry. can be specified
-    /// multiple times, must have the same number of names and emails.
-    author_emails: Vec<String>,
+    #[argh(option)]
+    /// a statement of the rights held in and over this entry, such as a
+    /// copyright notice
+    rights: Option<String>,
+
+    #[argh(option)]
+    /// the type of *rights*: text (the default), html, or xhtml
+    rights_type: Option<TextKind>,
+
+    #[argh(option)]
+    /// a contributor to this entry, in the same form as *author*. can be
+    /// specified multiple times.
+    contributor: Vec<StringablePerson>,
 
     #[argh(option, short = 'd')]
     /// the date and time, in RFC3339 format, when the entry was published
@@ -70,61 +136,522 @@ pub struct AddCommand {
     #[argh(option, short = 'D', default = "chrono::Utc::now()")]
     /// the date, in RFC3339 format, when the entry was most recently updated
     updated_at: DateTime<Utc>,
+
+    #[argh(switch, short = 'r')]
+    /// if an entry with this *id* already exists, replace it entirely
+    replace: bool,
+
+    #[argh(switch, short = 'u')]
+    /// if an entry with this *id* already exists, update only the fields
+    /// provided here, bumping *updated_at* if anything changed
+    upsert: bool,
 }
 
 impl KaboomCommand for AddCommand {
     fn run(&self, top_args: &crate::Kaboom) -> Result<()> {
-        if !self.author_emails.is_empty() && self.author_names.len() != self.author_emails.len() {
-            error!(
-                "author-names and author-emails must be provided the same number of times each if emails are provided at all, got {} and {}",
-                self.author_names.len(),
-                self.author_emails.len(),
-            );
+        if self.replace && self.upsert {
+            bail!("replace and upsert are mutually exclusive");
         }
 
+        let summary = inline_or_file(&self.summary, &self.summary_file, &self.content_file)?;
+        let content_type = self
+            .content_type
+            .clone()
+            .or_else(|| content_type_from_path(self.content_file.as_deref()?));
+        let content = read_content(
+            &self.content,
+            &self.content_file,
+            &self.summary_file,
+            content_type.as_deref(),
+        )?;
+        let summary = match (summary, &content) {
+            (Some(summary), _) => Some(
+                self.summary_type
+                    .unwrap_or_default()
+                    .text("summary", &summary)?,
+            ),
+            (None, Some(content)) if self.summary_from_content => {
+                summary_from_content(content, content_type.as_deref())?
+            }
+            (None, _) => None,
+        };
+        let (content, content_type) = render_markdown(content, content_type)?;
+
+        let _lock = Feed::lock(&top_args.file, top_args.wait, top_args.no_op)?;
         let mut feed = Feed::read_from_path(&top_args.file)?;
+        let original = feed.clone();
         let mut eb = EntryBuilder::default();
 
         eb.id(&self.id);
-        eb.title(self.title.clone());
-        eb.summary(self.summary.clone().map(|s| s.into()));
+        eb.title(
+            self.title_type
+                .unwrap_or_default()
+                .text("title", &self.title)?,
+        );
+        eb.summary(summary);
+        eb.rights(
+            self.rights
+                .as_deref()
+                .map(|it| self.rights_type.unwrap_or_default().text("rights", it))
+                .transpose()?,
+        );
         eb.published(self.published_at.map(|p| p.into()));
         eb.updated(self.updated_at);
-        eb.content(self.content.clone().map(|s| Content {
-            base: None,
-            content_type: self.content_type.clone(),
-            lang: self.content_language.clone(),
-            value: Some(s),
-            src: Some(self.id.clone()),
-        }));
-
-        if self.author_emails.is_empty() {
-            eb.contributors(
-                self.author_names
-                    .iter()
-                    .map(|name| Person {
-                        name: name.clone(),
-                        email: None,
-                        uri: None,
-                    })
-                    .collect::<Vec<Person>>(),
-            );
+        eb.content(content_from_args(
+            content,
+            self.content_src.clone(),
+            content_type,
+            self.content_language.clone(),
+        )?);
+
+        eb.authors(if self.author.is_empty() {
+            feed.authors().to_vec()
         } else {
-            eb.contributors(
-                zip(&self.author_names, &self.author_emails)
-                    .map(|(name, email)| Person {
-                        name: name.clone(),
-                        email: Some(email.clone()),
-                        uri: None,
-                    })
-                    .collect::<Vec<Person>>(),
-            );
+            self.author.iter().cloned().map(Person::from).collect()
+        });
+        eb.contributors(
+            self.contributor
+                .iter()
+                .cloned()
+                .map(Person::from)
+                .collect::<Vec<Person>>(),
+        );
+        eb.links(self.links());
+        eb.categories(
+            self.category
+                .iter()
+                .cloned()
+                .map(Category::from)
+                .collect::<Vec<Category>>(),
+        );
+
+        let mut entry = eb.build();
+        if let Some(content) = &mut entry.content {
+            encode_inline_content(content)?;
+        }
+        check_out_of_line_content(&entry)?;
+
+        if entry.authors().is_empty() {
+            warn!("entry has no authors, and neither does the feed; pass --author here or to kaboom meta");
         }
 
-        feed.entries.insert(0, eb.build());
+        match feed.entries.iter().position(|it| it.id() == self.id) {
+            None => feed.entries.insert(0, entry),
+            Some(idx) if self.replace => {
+                debug!("replacing existing entry {}", &self.id);
+                feed.entries[idx] = entry;
+            }
+            Some(idx) if self.upsert => {
+                if !self.merge_into(&mut feed.entries[idx], entry) {
+                    warn!("not writing anything because existing entry is already up to date");
+                    return Ok(());
+                }
+                check_out_of_line_content(&feed.entries[idx])?;
+            }
+            Some(_) => bail!(
+                "an entry with id {} already exists in the feed; pass --replace or --upsert to overwrite it",
+                self.id
+            ),
+        }
 
-        feed.write_to_path(&top_args.file)?;
+        feed.stamp_if_modified(&original, top_args.feed_updated, !top_args.no_generator);
+        feed.write_or_preview(&original, &top_args.file, top_args.no_op)?;
 
         Ok(())
     }
 }
+
+impl AddCommand {
+    /// The links given on the command line, plus a rel=alternate link to the
+    /// entry's *id* if it's an http(s) URL and no other alternate was given,
+    /// since many readers need one to open the entry at all.
+    fn links(&self) -> Vec<AtomLink> {
+        let mut links: Vec<AtomLink> = self.link.iter().cloned().map(AtomLink::from).collect();
+
+        let is_http = self.id.starts_with("http://") || self.id.starts_with("https://");
+        if is_http && !links.iter().any(|it| it.rel() == "alternate") {
+            links.insert(
+                0,
+                AtomLink {
+                    href: self.id.clone(),
+                    rel: "alternate".into(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        links
+    }
+
+    /// Copy over whichever fields of *new* were actually provided on the
+    /// command line into *existing*, bumping *updated* if anything changed.
+    /// Returns whether anything changed.
+    fn merge_into(&self, existing: &mut AtomEntry, new: AtomEntry) -> bool {
+        let mut any_updates = false;
+
+        if existing.title != new.title {
+            existing.set_title(new.title);
+            any_updates = true;
+        }
+
+        if new.summary.is_some() && existing.summary != new.summary {
+            existing.set_summary(new.summary);
+            any_updates = true;
+        }
+
+        if !self.link.is_empty() && existing.links != new.links {
+            existing.set_links(new.links);
+            any_updates = true;
+        }
+
+        if !new.categories.is_empty() && existing.categories != new.categories {
+            existing.set_categories(new.categories);
+            any_updates = true;
+        }
+
+        if new.rights.is_some() && existing.rights != new.rights {
+            existing.set_rights(new.rights);
+            any_updates = true;
+        }
+
+        if new.published.is_some() && existing.published != new.published {
+            existing.set_published(new.published);
+            any_updates = true;
+        }
+
+        if new.content.is_some() && existing.content != new.content {
+            existing.set_content(new.content);
+            any_updates = true;
+        }
+
+        if !self.author.is_empty() && existing.authors != new.authors {
+            existing.set_authors(new.authors);
+            any_updates = true;
+        }
+
+        if !new.contributors.is_empty() && existing.contributors != new.contributors {
+            existing.set_contributors(new.contributors);
+            any_updates = true;
+        }
+
+        if any_updates {
+            existing.set_updated(new.updated);
+        }
+
+        any_updates
+    }
+}
+
+#[test]
+fn merge_into_behavior() {
+    let then: DateTime<Utc> = "2023-01-01T00:00:00Z".parse().unwrap();
+    let now: DateTime<Utc> = "2023-06-01T00:00:00Z".parse().unwrap();
+    let author = |name: &str| Person {
+        name: name.into(),
+        ..Default::default()
+    };
+    let existing = AtomEntry {
+        id: "urn:x:1".into(),
+        title: "Title".into(),
+        summary: Some("Summary".into()),
+        updated: then.into(),
+        authors: vec![author("Original Author")],
+        ..Default::default()
+    };
+    // What run would build for the same command line, with the feed's own
+    // author filled in for the omitted --author.
+    let new = |summary: &str| AtomEntry {
+        summary: Some(summary.into()),
+        updated: now.into(),
+        authors: vec![author("Feed Author")],
+        ..existing.clone()
+    };
+    let add =
+        AddCommand::from_args(&["add"], &["urn:x:1", "Title", "-s", "Summary", "-u"]).unwrap();
+
+    // Re-running the same command changes nothing.
+    let mut entry = existing.clone();
+    assert!(!add.merge_into(&mut entry, new("Summary")));
+    assert_eq!(existing, entry);
+
+    // A changed summary is taken and bumps updated, but without --author the
+    // existing authors stay.
+    let mut entry = existing.clone();
+    assert!(add.merge_into(&mut entry, new("A new summary")));
+    assert_eq!(Some(&"A new summary".into()), entry.summary());
+    assert_eq!(now, *entry.updated());
+    assert_eq!(vec![author("Original Author")], entry.authors);
+}
+
+/// Resolve a string argument that can be given either *inline* or as a path to
+/// a *file* (where - means standard input). *other_file* is the sibling file
+/// argument of the same command, used to make sure standard input is only
+/// claimed once.
+pub fn inline_or_file(
+    inline: &Option<String>,
+    file: &Option<PathBuf>,
+    other_file: &Option<PathBuf>,
+) -> Result<Option<String>> {
+    inline_or_file_bytes(inline, file, other_file)?
+        .map(text_from_bytes)
+        .transpose()
+}
+
+fn text_from_bytes(bytes: Vec<u8>) -> Result<String> {
+    String::from_utf8(bytes).map_err(|_| {
+        anyhow!("got text that is not valid UTF-8; for binary content, pass its MIME type as the content type")
+    })
+}
+
+/// Like [inline_or_file], but for arguments that might not be text.
+pub fn inline_or_file_bytes(
+    inline: &Option<String>,
+    file: &Option<PathBuf>,
+    other_file: &Option<PathBuf>,
+) -> Result<Option<Vec<u8>>> {
+    let stdin_path = Path::new("-");
+
+    match (inline, file) {
+        (Some(_), Some(path)) => bail!(
+            "got both an inline value and a file ({}), pick one",
+            path.to_string_lossy()
+        ),
+        (Some(it), None) => Ok(Some(it.clone().into_bytes())),
+        (None, None) => Ok(None),
+        (None, Some(path)) if path == stdin_path => {
+            if other_file.as_deref() == Some(stdin_path) {
+                bail!("only one argument can be read from standard input");
+            }
+
+            let mut buf = Vec::new();
+            std::io::stdin().read_to_end(&mut buf)?;
+            Ok(Some(buf))
+        }
+        (None, Some(path)) => {
+            Ok(Some(std::fs::read(path).with_context(|| {
+                format!("could not read {}", path.to_string_lossy())
+            })?))
+        }
+    }
+}
+
+/// Read the content of an entry from either *inline* or *file* (see
+/// [inline_or_file]), and decode it with [decode_content].
+pub fn read_content(
+    inline: &Option<String>,
+    file: &Option<PathBuf>,
+    other_file: &Option<PathBuf>,
+    content_type: Option<&str>,
+) -> Result<Option<String>> {
+    decode_content(
+        inline_or_file_bytes(inline, file, other_file)?,
+        content_type,
+    )
+}
+
+/// Turn the raw *bytes* of an entry's content into the value to store, after
+/// making sure *content_type* is one we know how to carry. Content of a binary
+/// MIME type comes back base64-encoded, as RFC 4287 section 4.1.3.3 requires.
+pub fn decode_content(
+    bytes: Option<Vec<u8>>,
+    content_type: Option<&str>,
+) -> Result<Option<String>> {
+    let bytes = match bytes {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+
+    if content_type != Some("markdown") {
+        check_content_type(content_type)?;
+    }
+
+    if content_type.map_or(false, is_base64_type) {
+        Ok(Some(base64_encode(&bytes)))
+    } else {
+        text_from_bytes(bytes).map(Some)
+    }
+}
+
+/// Get inline *content* ready to be written, which for xhtml means making sure
+/// it's well-formed and wrapped in the xhtml div that RFC 4287 section 4.1.3.3
+/// requires (adding the div only if it isn't already there).
+pub fn encode_inline_content(content: &mut Content) -> Result<()> {
+    if content.src().is_some() {
+        return Ok(());
+    }
+
+    check_content_type(content.content_type())?;
+
+    if content.content_type() == Some("xhtml") {
+        if let Some(value) = content.value() {
+            content.set_value(xhtml_div(value).context("xhtml content is not well-formed XML")?);
+        }
+    }
+
+    Ok(())
+}
+
+/// Guess an Atom content type from a file extension, for when *content_type*
+/// isn't given explicitly.
+pub fn content_type_from_path(path: &Path) -> Option<String> {
+    match path.extension()?.to_str()? {
+        "html" | "htm" => Some("html".into()),
+        "xhtml" => Some("xhtml".into()),
+        "txt" => Some("text".into()),
+        "md" | "markdown" => Some("markdown".into()),
+        _ => None,
+    }
+}
+
+#[test]
+fn content_type_from_path_behavior() {
+    assert_eq!(
+        Some("html".to_string()),
+        content_type_from_path(Path::new("posts/001-foobar.html"))
+    );
+    assert_eq!(
+        Some("xhtml".to_string()),
+        content_type_from_path(Path::new("001-foobar.xhtml"))
+    );
+    assert_eq!(
+        Some("text".to_string()),
+        content_type_from_path(Path::new("001-foobar.txt"))
+    );
+    assert_eq!(
+        Some("markdown".to_string()),
+        content_type_from_path(Path::new("001-foobar.md"))
+    );
+    assert_eq!(None, content_type_from_path(Path::new("001-foobar.png")));
+    assert_eq!(None, content_type_from_path(Path::new("-")));
+}
+
+/// Markdown isn't a content type Atom knows anything about, so if that's what
+/// we were handed, render it to HTML before it goes anywhere near the feed.
+/// Anything else passes through untouched.
+pub fn render_markdown(
+    content: Option<String>,
+    content_type: Option<String>,
+) -> Result<(Option<String>, Option<String>)> {
+    match (content, content_type.as_deref()) {
+        #[cfg(feature = "markdown")]
+        (Some(content), Some("markdown")) => {
+            Ok((Some(crate::markdown::render(&content)), Some("html".into())))
+        }
+        #[cfg(not(feature = "markdown"))]
+        (Some(_), Some("markdown")) => {
+            bail!("this build of kaboom does not support Markdown; rebuild it with --features markdown")
+        }
+        (None, Some("markdown")) => {
+            bail!("Markdown is only supported when providing new content to render")
+        }
+        (content, _) => Ok((content, content_type)),
+    }
+}
+
+/// Derive a summary from the first paragraph of *content*, which must be
+/// Markdown.
+pub fn summary_from_content(content: &str, content_type: Option<&str>) -> Result<Option<Text>> {
+    if content_type != Some("markdown") {
+        warn!("not deriving a summary from content because it is not Markdown");
+        return Ok(None);
+    }
+
+    #[cfg(feature = "markdown")]
+    return Ok(crate::markdown::first_paragraph(content).map(Text::html));
+
+    #[cfg(not(feature = "markdown"))]
+    {
+        let _ = content;
+        bail!("this build of kaboom does not support Markdown; rebuild it with --features markdown")
+    }
+}
+
+/// Build the *content* block of an entry from the arguments shared by `kaboom
+/// add` and `kaboom edit`: either inline content (*value*), or out-of-line
+/// content found at *src*, which per RFC 4287 section 4.1.3.2 must be empty.
+pub fn content_from_args(
+    value: Option<String>,
+    src: Option<String>,
+    content_type: Option<String>,
+    lang: Option<String>,
+) -> Result<Option<Content>> {
+    if value.is_some() && src.is_some() {
+        bail!("got both inline content and --content-src, pick one");
+    }
+
+    if value.is_none() && src.is_none() {
+        return Ok(None);
+    }
+
+    Ok(Some(Content {
+        base: None,
+        content_type,
+        lang,
+        value,
+        src,
+    }))
+}
+
+#[test]
+fn content_from_args_behavior() {
+    let inline = content_from_args(Some("hi".into()), None, Some("text".into()), None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(Some("hi"), inline.value());
+    assert_eq!(None, inline.src());
+
+    let out_of_line = content_from_args(
+        None,
+        Some("https://example.com/001.mp3".into()),
+        Some("audio/mpeg".into()),
+        None,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(None, out_of_line.value());
+    assert_eq!(Some("https://example.com/001.mp3"), out_of_line.src());
+
+    assert!(content_from_args(None, None, Some("text".into()), None)
+        .unwrap()
+        .is_none());
+    assert!(content_from_args(
+        Some("hi".into()),
+        Some("https://example.com/001.mp3".into()),
+        None,
+        None
+    )
+    .is_err());
+}
+
+/// Refuse to write an entry whose out-of-line content breaks RFC 4287: such
+/// content must be empty (section 4.1.3.2), must have a MIME type if it has a
+/// type at all, which we go further and require (section 4.1.3.1), and the
+/// entry must then have a summary (section 4.1.1.1), as must entries with
+/// base64-encoded content.
+pub fn check_out_of_line_content(entry: &AtomEntry) -> Result<()> {
+    let content = match entry.content() {
+        Some(content) if content.src().is_some() => content,
+        Some(content) if content.content_type().map_or(false, is_base64_type) => {
+            if entry.summary().is_none() {
+                bail!("entries with binary content must have a summary");
+            }
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+
+    if content.value().is_some() {
+        bail!("content with a src must be empty; pass either inline content or --content-src, not both");
+    }
+
+    match content.content_type() {
+        Some(it) if is_mime_type(it) => {}
+        _ => bail!("content with a src needs a MIME type (such as audio/mpeg) as its content type"),
+    }
+
+    if entry.summary().is_none() {
+        bail!("entries with out-of-line content must have a summary");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,440 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+//! Paged archives for pruned entries, per RFC 5005 ("Feed Paging and
+//! Archiving"), section 4. Rather than one ever-growing reject file, pruned
+//! entries are rolled into one archive document per year or month, chained
+//! together (and to the live feed) with prev-archive and next-archive links,
+//! so that feed readers can reconstruct the full history.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use atom_syndication::extension::Extension;
+use atom_syndication::{Entry as AtomEntry, Feed, FixedDateTime, Link as AtomLink};
+use log::debug;
+
+use crate::kaboom_entry::{DateOrder, KaboomEntry};
+use crate::kaboom_feed::{merge_entries, KaboomFeed};
+
+pub const FH_NAMESPACE: &str = "http://purl.org/syndication/history/1.0";
+
+#[derive(Clone, Copy, Eq, Debug, PartialEq)]
+pub enum ArchivePeriod {
+    Year,
+    Month,
+}
+
+impl FromStr for ArchivePeriod {
+    type Err = &'static str;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        match it {
+            "year" => Ok(Self::Year),
+            "month" => Ok(Self::Month),
+            _ => Err("unknown archive period"),
+        }
+    }
+}
+
+impl ArchivePeriod {
+    /// The label used in archive file names for entries dated *date*, for
+    /// example 2023 or 2024-03.
+    pub fn label(&self, date: &FixedDateTime) -> String {
+        match self {
+            Self::Year => date.format("%Y").to_string(),
+            Self::Month => date.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+/// The path of the archive document for *label*, next to the live feed: for
+/// feed.xml and 2023, that's feed-2023.xml.
+pub fn archive_path(feed_path: &Path, label: &str) -> PathBuf {
+    let stem = feed_path
+        .file_stem()
+        .map_or("feed".into(), |s| s.to_string_lossy());
+    let ext = feed_path
+        .extension()
+        .map_or("xml".into(), |e| e.to_string_lossy());
+
+    feed_path.with_file_name(format!("{}-{}.{}", stem, label, ext))
+}
+
+#[test]
+fn archive_path_behavior() {
+    assert_eq!(
+        PathBuf::from("blog/feed-2023.xml"),
+        archive_path(Path::new("blog/feed.xml"), "2023"),
+    );
+    assert_eq!(
+        PathBuf::from("atom-2024-03.xml"),
+        archive_path(Path::new("atom.xml"), "2024-03"),
+    );
+}
+
+/// Put archive *labels* in chronological order of the start of the period
+/// each covers, so that a year's archive comes before those of its months,
+/// however the labels happen to sort as strings.
+fn chronological(labels: BTreeSet<String>) -> Vec<String> {
+    let period_start = |label: &str| -> (u32, u32) {
+        let (year, month) = label.split_once('-').unwrap_or((label, "0"));
+        (year.parse().unwrap_or(0), month.parse().unwrap_or(0))
+    };
+
+    let mut labels: Vec<String> = labels.into_iter().collect();
+    labels.sort_by_key(|label| period_start(label));
+    labels
+}
+
+#[test]
+fn chronological_behavior() {
+    let labels = ["2023-12", "2024", "2023", "2022-01", "2023-02"]
+        .iter()
+        .map(|it| it.to_string())
+        .collect();
+
+    assert_eq!(
+        vec!["2022-01", "2023", "2023-02", "2023-12", "2024"],
+        chronological(labels),
+    );
+}
+
+/// Find the labels of all archive documents that already exist next to the
+/// live feed.
+fn existing_labels(feed_path: &Path) -> Result<BTreeSet<String>> {
+    let dir = match feed_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut labels = BTreeSet::new();
+
+    for dirent in std::fs::read_dir(dir)? {
+        let path = dirent?.path();
+        let label = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| label_from_file_name(feed_path, name));
+
+        if let Some(label) = label {
+            labels.insert(label);
+        }
+    }
+
+    Ok(labels)
+}
+
+fn label_from_file_name(feed_path: &Path, name: &str) -> Option<String> {
+    let expected = archive_path(feed_path, "");
+    let expected = expected.file_name()?.to_str()?;
+    // expected is now something like "feed-.xml"; split it around the label.
+    let (prefix, suffix) = expected.split_at(expected.rfind('.')?);
+    let label = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+    let is_digits =
+        |it: &str, len: usize| it.len() == len && it.chars().all(|c| c.is_ascii_digit());
+
+    match label.split_once('-') {
+        None if is_digits(label, 4) => Some(label.to_string()),
+        Some((year, month)) if is_digits(year, 4) && is_digits(month, 2) => Some(label.to_string()),
+        _ => None,
+    }
+}
+
+#[test]
+fn label_from_file_name_behavior() {
+    let feed = Path::new("blog/feed.xml");
+
+    assert_eq!(
+        Some("2023".to_string()),
+        label_from_file_name(feed, "feed-2023.xml")
+    );
+    assert_eq!(
+        Some("2024-03".to_string()),
+        label_from_file_name(feed, "feed-2024-03.xml")
+    );
+    assert_eq!(None, label_from_file_name(feed, "feed.rej.xml"));
+    assert_eq!(None, label_from_file_name(feed, "feed-draft.xml"));
+    assert_eq!(None, label_from_file_name(feed, "other-2023.xml"));
+}
+
+/// The URL at which a file named *file_name* will be served, assuming it sits
+/// next to the live feed.
+fn sibling_url(feed: &Feed, file_name: &str) -> Result<String> {
+    let base = current_url(feed).ok_or_else(|| {
+        anyhow!("archiving requires the feed to have a rel=self link (or an http(s) id); add one with kaboom meta -r")
+    })?;
+    // Swap out the last path segment of the live feed's URL, taking care not
+    // to mistake the slashes in the scheme for a path.
+    let authority_end = base.find("://").map_or(0, |idx| idx + 3);
+    let dir_end = base[authority_end..]
+        .rfind('/')
+        .map_or(base.len(), |idx| authority_end + idx);
+
+    Ok(format!("{}/{}", &base[..dir_end], file_name))
+}
+
+fn archive_url(feed: &Feed, feed_path: &Path, label: &str) -> Result<String> {
+    let path = archive_path(feed_path, label);
+    sibling_url(
+        feed,
+        &path.file_name().unwrap_or_default().to_string_lossy(),
+    )
+}
+
+/// The URL of the live feed itself: its rel=self link, or its id if that's an
+/// http(s) URL.
+fn current_url(feed: &Feed) -> Option<&str> {
+    feed.links()
+        .iter()
+        .find(|link| link.rel() == "self")
+        .map(|link| link.href())
+        .or_else(|| Some(feed.id()).filter(|id| id.starts_with("http")))
+}
+
+/// Replace any links with *rel* in *links* with a single link to *href*, or
+/// with nothing at all if *href* is None.
+fn set_rel_link(links: &mut Vec<AtomLink>, rel: &str, href: Option<String>) {
+    links.retain(|link| link.rel() != rel);
+
+    if let Some(href) = href {
+        links.push(AtomLink {
+            href,
+            rel: rel.into(),
+            mime_type: Some("application/atom+xml".into()),
+            ..Default::default()
+        });
+    }
+}
+
+fn mark_as_archive(feed: &mut Feed) {
+    feed.namespaces.insert("fh".into(), FH_NAMESPACE.into());
+    feed.extensions.entry("fh".into()).or_default().insert(
+        "archive".into(),
+        vec![Extension {
+            name: "fh:archive".into(),
+            ..Default::default()
+        }],
+    );
+}
+
+/// Roll *rejected* into archive documents next to the live *feed* at
+/// *feed_path*, one per *period*, and re-link the whole chain of archives
+/// (plus the live feed's prev-archive link) to match. The live feed itself is
+/// modified but not written; that's left to the caller.
+pub fn archive_entries(
+    feed: &mut Feed,
+    rejected: Vec<AtomEntry>,
+    feed_path: &Path,
+    period: ArchivePeriod,
+    order: DateOrder,
+    no_op: bool,
+) -> Result<()> {
+    let mut incoming: BTreeMap<String, Vec<AtomEntry>> = BTreeMap::new();
+
+    for entry in rejected {
+        let label = period.label(entry.date(order));
+        incoming.entry(label).or_default().push(entry);
+    }
+
+    let mut labels = existing_labels(feed_path)?;
+    labels.extend(incoming.keys().cloned());
+    let labels = chronological(labels);
+
+    for (idx, label) in labels.iter().enumerate() {
+        let path = archive_path(feed_path, label);
+        let original = if path.exists() {
+            Feed::read_from_path(&path).with_context(|| {
+                format!(
+                    "refusing to overwrite unreadable archive {}",
+                    path.to_string_lossy()
+                )
+            })?
+        } else {
+            Feed::default()
+        };
+
+        let mut archive = original.clone();
+        if let Some(new_entries) = incoming.remove(label) {
+            debug!("archiving {} entries to {}", new_entries.len(), label);
+            archive = feed.clone();
+            archive.set_entries(merge_entries(
+                original.entries().to_vec(),
+                new_entries,
+                order,
+            ));
+            mark_as_archive(&mut archive);
+        }
+
+        set_rel_link(
+            &mut archive.links,
+            "self",
+            Some(archive_url(feed, feed_path, label)?),
+        );
+        set_rel_link(
+            &mut archive.links,
+            "current",
+            current_url(feed).map(String::from),
+        );
+        set_rel_link(
+            &mut archive.links,
+            "prev-archive",
+            idx.checked_sub(1)
+                .map(|prev| archive_url(feed, feed_path, &labels[prev]))
+                .transpose()?,
+        );
+        set_rel_link(
+            &mut archive.links,
+            "next-archive",
+            labels
+                .get(idx + 1)
+                .map(|next| archive_url(feed, feed_path, next))
+                .transpose()?,
+        );
+
+        if archive != original {
+            archive.write_or_preview(&original, &path, no_op)?;
+        }
+    }
+
+    link_newest_archive(feed, labels.last(), feed_path)
+}
+
+/// Point the live *feed*'s prev-archive link at the archive that
+/// [archive_entries] would leave newest after archiving *rejected*, without
+/// writing anything, so the live feed can be sized before it's written.
+pub fn link_archives_to_come(
+    feed: &mut Feed,
+    rejected: &[AtomEntry],
+    feed_path: &Path,
+    period: ArchivePeriod,
+    order: DateOrder,
+) -> Result<()> {
+    let mut labels = existing_labels(feed_path)?;
+    labels.extend(rejected.iter().map(|it| period.label(it.date(order))));
+
+    link_newest_archive(feed, chronological(labels).last(), feed_path)
+}
+
+fn link_newest_archive(feed: &mut Feed, newest: Option<&String>, feed_path: &Path) -> Result<()> {
+    if let Some(newest) = newest {
+        let href = archive_url(feed, feed_path, newest)?;
+        set_rel_link(&mut feed.links, "prev-archive", Some(href));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn sibling_url_behavior() {
+    let mut feed = Feed {
+        id: "https://example.com/blog/feed.xml".into(),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        "https://example.com/blog/feed-2023.xml",
+        sibling_url(&feed, "feed-2023.xml").unwrap(),
+    );
+
+    feed.id = "urn:uuid:60a76c80-d399-11d9-b93C-0003939e0af6".into();
+    assert!(sibling_url(&feed, "feed-2023.xml").is_err());
+
+    feed.links.push(AtomLink {
+        href: "https://example.com".into(),
+        rel: "self".into(),
+        ..Default::default()
+    });
+    assert_eq!(
+        "https://example.com/feed-2023.xml",
+        sibling_url(&feed, "feed-2023.xml").unwrap(),
+    );
+}
+
+#[test]
+fn archive_entries_behavior() {
+    let dir = std::env::temp_dir().join(format!("kaboom-archive-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let feed_path = dir.join("feed.xml");
+
+    let mut feed = Feed {
+        title: "T".into(),
+        id: "https://example.com/feed.xml".into(),
+        ..Default::default()
+    };
+    let entry = |id: &str, updated: &str| AtomEntry {
+        id: format!("https://example.com/{}", id),
+        updated: updated.parse().unwrap(),
+        ..Default::default()
+    };
+    let links_of = |label: &str| -> Vec<(String, String)> {
+        let archive = Feed::read_from_path(&archive_path(&feed_path, label)).unwrap();
+        let mut links: Vec<(String, String)> = archive
+            .links()
+            .iter()
+            .filter(|it| it.rel().ends_with("-archive"))
+            .map(|it| (it.rel().to_string(), it.href().to_string()))
+            .collect();
+        links.sort();
+        links
+    };
+    let url = |label: &str| format!("https://example.com/feed-{}.xml", label);
+
+    archive_entries(
+        &mut feed,
+        vec![
+            entry("a", "2023-03-01T00:00:00Z"),
+            entry("b", "2022-03-01T00:00:00Z"),
+        ],
+        &feed_path,
+        ArchivePeriod::Year,
+        DateOrder::UpdatedOnly,
+        false,
+    )
+    .unwrap();
+    archive_entries(
+        &mut feed,
+        vec![entry("c", "2023-12-01T00:00:00Z")],
+        &feed_path,
+        ArchivePeriod::Month,
+        DateOrder::UpdatedOnly,
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(
+        vec![("next-archive".to_string(), url("2023"))],
+        links_of("2022"),
+    );
+    assert_eq!(
+        vec![
+            ("next-archive".to_string(), url("2023-12")),
+            ("prev-archive".to_string(), url("2022")),
+        ],
+        links_of("2023"),
+    );
+    assert_eq!(
+        vec![("prev-archive".to_string(), url("2023"))],
+        links_of("2023-12"),
+    );
+    assert_eq!(
+        Some(url("2023-12").as_str()),
+        feed.links()
+            .iter()
+            .find(|it| it.rel() == "prev-archive")
+            .map(|it| it.href()),
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,239 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+//! Crash- and permission-safe replacement of files on disk: the new contents
+//! are written to a uniquely-named temp file next to the real target, synced,
+//! given the target's permissions (and, on Unix, ownership), and then renamed
+//! over the target, so readers only ever see the old file or the new one.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use log::{debug, warn};
+
+/// How many symlinks we're willing to follow before assuming a loop, matching
+/// Linux's own limit.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
+/// Follow *path* through any symlinks to the file that should actually be
+/// written, which need not exist yet.
+pub fn resolve_symlinks(path: &Path) -> Result<PathBuf> {
+    let mut path = PathBuf::from(path);
+
+    for _ in 0..MAX_SYMLINK_DEPTH {
+        match std::fs::symlink_metadata(&path) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                let link = std::fs::read_link(&path)?;
+                debug!(
+                    "following symlink {} to {}",
+                    path.to_string_lossy(),
+                    link.to_string_lossy()
+                );
+                path = match path.parent() {
+                    Some(parent) if link.is_relative() => parent.join(link),
+                    _ => link,
+                };
+            }
+            _ => return Ok(path),
+        }
+    }
+
+    bail!(
+        "too many levels of symlinks resolving {}",
+        path.to_string_lossy()
+    )
+}
+
+/// Replace the file at *path* (or, if it's a symlink, the file it points to)
+/// with whatever *write* writes. If anything fails along the way, the temp file
+/// is removed and the original is left untouched.
+pub fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    let target = resolve_symlinks(path)?;
+    let (temp_path, file) = create_temp_file(&target)?;
+    debug!("writing to temp file {}", temp_path.to_string_lossy());
+
+    let result = fill_and_replace(&target, &temp_path, file, write);
+
+    if result.is_err() {
+        if let Err(err) = std::fs::remove_file(&temp_path) {
+            if err.kind() != ErrorKind::NotFound {
+                warn!(
+                    "could not clean up temp file {}: {}",
+                    temp_path.to_string_lossy(),
+                    err
+                );
+            }
+        }
+    }
+
+    result
+}
+
+/// Create a new, empty, uniquely-named file alongside *target*, named like
+/// .feed.xml.1234-0.kaboom so it's hidden and obviously ours.
+fn create_temp_file(target: &Path) -> Result<(PathBuf, File)> {
+    let name = target
+        .file_name()
+        .with_context(|| format!("{} is not a file", target.to_string_lossy()))?
+        .to_string_lossy();
+
+    for attempt in 0.. {
+        let temp_path = target.with_file_name(format!(
+            ".{}.{}-{}.kaboom",
+            name,
+            std::process::id(),
+            attempt
+        ));
+
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("could not create temp file {}", temp_path.to_string_lossy())
+                })
+            }
+        }
+    }
+
+    unreachable!()
+}
+
+fn fill_and_replace(
+    target: &Path,
+    temp_path: &Path,
+    file: File,
+    write: impl FnOnce(&mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)?;
+    let file = writer.into_inner().map_err(|err| err.into_error())?;
+
+    match std::fs::metadata(target) {
+        Ok(meta) => {
+            file.set_permissions(meta.permissions())?;
+            copy_ownership(&file, &meta, target);
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+
+    file.sync_all()?;
+    drop(file);
+
+    debug!(
+        "renaming temp file {} to final path {}",
+        temp_path.to_string_lossy(),
+        target.to_string_lossy(),
+    );
+    std::fs::rename(temp_path, target)?;
+
+    // The target has been replaced by now, so failing to make that durable
+    // mustn't be reported as a failed write, lest the caller assume nothing
+    // changed and try again.
+    if let Err(err) = sync_parent_dir(target) {
+        warn!(
+            "wrote {}, but could not sync its directory, so the change may not survive a crash: {}",
+            target.to_string_lossy(),
+            err
+        );
+    }
+
+    Ok(())
+}
+
+/// Give *file* the same owner and group as *meta*, as far as we're allowed to.
+/// Only root can give files away, but anyone can keep a file in a group they
+/// belong to, which is the case that matters for feeds shared with a web
+/// server, so failing to set the owner isn't fatal.
+#[cfg(unix)]
+fn copy_ownership(file: &File, meta: &std::fs::Metadata, target: &Path) {
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+
+    // SAFETY: fd is a valid, open file descriptor for as long as *file* lives.
+    if unsafe { libc::fchown(fd, meta.uid(), meta.gid()) } == 0 {
+        return;
+    }
+
+    // SAFETY: as above; a uid of -1 leaves the owner unchanged.
+    if unsafe { libc::fchown(fd, u32::MAX, meta.gid()) } != 0 {
+        warn!(
+            "could not preserve the ownership of {}: {}",
+            target.to_string_lossy(),
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_file: &File, _meta: &std::fs::Metadata, _target: &Path) {}
+
+/// Make the rename of *target* itself durable, which on Unix means syncing the
+/// directory that contains it.
+#[cfg(unix)]
+fn sync_parent_dir(target: &Path) -> Result<()> {
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_target: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn write_atomically_behavior() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    let dir = std::env::temp_dir().join(format!("kaboom-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let real = dir.join("real.xml");
+    let link = dir.join("feed.xml");
+    std::fs::write(&real, "old").unwrap();
+    std::fs::set_permissions(&real, std::fs::Permissions::from_mode(0o640)).unwrap();
+    symlink("real.xml", &link).unwrap();
+
+    write_atomically(&link, |out| Ok(out.write_all(b"new")?)).unwrap();
+    assert!(std::fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!("new", std::fs::read_to_string(&real).unwrap());
+    assert_eq!(
+        0o640,
+        std::fs::metadata(&real).unwrap().permissions().mode() & 0o777
+    );
+
+    assert!(write_atomically(&link, |_| bail!("oh no")).is_err());
+    assert_eq!("new", std::fs::read_to_string(&real).unwrap());
+    assert_eq!(2, std::fs::read_dir(&dir).unwrap().count());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
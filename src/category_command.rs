@@ -0,0 +1,230 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use anyhow::Result;
+use argh::FromArgs;
+use atom_syndication::{Category, Entry as AtomEntry, Feed};
+use log::warn;
+
+use crate::json::{JsonValue, OutputFormat};
+use crate::kaboom_command::KaboomCommand;
+use crate::kaboom_feed::KaboomFeed;
+use crate::Kaboom;
+
+#[derive(FromArgs, Debug)]
+/// Manage the categories (tags) of the feed's entries in bulk. With no further
+/// subcommand, lists every category term in use along with how many entries
+/// have it. Entries' *updated_at* is left alone by renames and removals, since
+/// recategorizing isn't news to readers.
+#[argh(subcommand, name = "category")]
+pub struct CategoryCommand {
+    #[argh(subcommand)]
+    action: Option<CategoryAction>,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand)]
+enum CategoryAction {
+    List(CategoryListCommand),
+    Rename(CategoryRenameCommand),
+    Rm(CategoryRmCommand),
+}
+
+#[derive(FromArgs, Debug)]
+/// List every category term in use, with how many entries have it, most used
+/// first.
+#[argh(subcommand, name = "list")]
+struct CategoryListCommand {}
+
+#[derive(FromArgs, Debug)]
+/// Rename a category term on every entry that has it, keeping its scheme and
+/// label. Entries that already have the new term just lose the old one.
+#[argh(subcommand, name = "rename")]
+struct CategoryRenameCommand {
+    #[argh(positional)]
+    /// the term to rename
+    from: String,
+
+    #[argh(positional)]
+    /// the new term
+    to: String,
+}
+
+#[derive(FromArgs, Debug)]
+/// Remove category terms from every entry that has them.
+#[argh(subcommand, name = "rm")]
+struct CategoryRmCommand {
+    #[argh(positional)]
+    /// the term(s) to remove
+    terms: Vec<String>,
+}
+
+impl KaboomCommand for CategoryCommand {
+    fn run(&self, top_args: &Kaboom) -> Result<()> {
+        match &self.action {
+            None | Some(CategoryAction::List(_)) => list(top_args),
+            Some(CategoryAction::Rename(rename)) => modify(top_args, |entry| {
+                rename_category(entry, &rename.from, &rename.to)
+            }),
+            Some(CategoryAction::Rm(rm)) => {
+                if rm.terms.is_empty() {
+                    warn!("not removing anything because no terms were given");
+                    return Ok(());
+                }
+
+                modify(top_args, |entry| {
+                    let before = entry.categories.len();
+                    entry
+                        .categories
+                        .retain(|it| !rm.terms.iter().any(|term| term == it.term()));
+                    entry.categories.len() != before
+                })
+            }
+        }
+    }
+}
+
+fn list(top_args: &Kaboom) -> Result<()> {
+    let feed = Feed::read_from_path(&top_args.file)?;
+    let counts = term_counts(feed.entries());
+    let as_json = |(term, count): &(&str, usize)| {
+        JsonValue::Object(vec![
+            ("term", (*term).into()),
+            ("count", (*count as u64).into()),
+        ])
+    };
+
+    match top_args.format {
+        OutputFormat::Human => {
+            for (term, count) in &counts {
+                println!("term={}\tcount={}", term, count);
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", JsonValue::Array(counts.iter().map(as_json).collect()))
+        }
+        OutputFormat::JsonLines => {
+            for it in &counts {
+                println!("{}", as_json(it));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply *change* to every entry of the feed, writing it back out if any of
+/// them report having changed.
+fn modify(top_args: &Kaboom, mut change: impl FnMut(&mut AtomEntry) -> bool) -> Result<()> {
+    let _lock = Feed::lock(&top_args.file, top_args.wait, top_args.no_op)?;
+    let mut feed = Feed::read_from_path(&top_args.file)?;
+    let original = feed.clone();
+
+    let mut changed = 0;
+    for entry in &mut feed.entries {
+        if change(entry) {
+            changed += 1;
+        }
+    }
+
+    if changed == 0 {
+        warn!("not writing anything because no entries have that category");
+        return Ok(());
+    }
+
+    feed.stamp_if_modified(&original, top_args.feed_updated, !top_args.no_generator);
+    feed.write_or_preview(&original, &top_args.file, top_args.no_op)?;
+
+    Ok(())
+}
+
+/// How many *entries* have each category term, most used first, and
+/// alphabetically among equally used terms.
+fn term_counts(entries: &[AtomEntry]) -> Vec<(&str, usize)> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+
+    for entry in entries {
+        let mut terms: Vec<&str> = entry.categories().iter().map(|it| it.term()).collect();
+        terms.sort_unstable();
+        terms.dedup();
+
+        for term in terms {
+            match counts.iter_mut().find(|(it, _)| *it == term) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((term, 1)),
+            }
+        }
+    }
+
+    counts.sort_by(|(a_term, a_count), (b_term, b_count)| {
+        b_count.cmp(a_count).then(a_term.cmp(b_term))
+    });
+    counts
+}
+
+/// Rename the category *from* to *to* on *entry*, dropping any resulting
+/// duplicates. Returns whether anything changed.
+fn rename_category(entry: &mut AtomEntry, from: &str, to: &str) -> bool {
+    if from == to || !entry.categories().iter().any(|it| it.term() == from) {
+        return false;
+    }
+
+    let mut renamed = Vec::with_capacity(entry.categories().len());
+    for mut category in entry.categories.drain(..) {
+        if category.term() == from {
+            category.set_term(to);
+        }
+
+        let duplicate = category.term() == to
+            && renamed
+                .iter()
+                .any(|it: &Category| it.term() == to && it.scheme() == category.scheme());
+        if !duplicate {
+            renamed.push(category);
+        }
+    }
+    entry.set_categories(renamed);
+
+    true
+}
+
+#[test]
+fn category_changes_behavior() {
+    let category = |term: &str| Category {
+        term: term.into(),
+        ..Default::default()
+    };
+    let mut entries = vec![
+        AtomEntry {
+            categories: vec![category("rust"), category("cli"), category("rust")],
+            ..Default::default()
+        },
+        AtomEntry {
+            categories: vec![category("cli"), category("rustlang")],
+            ..Default::default()
+        },
+        AtomEntry::default(),
+    ];
+
+    assert_eq!(
+        vec![("cli", 2), ("rust", 1), ("rustlang", 1)],
+        term_counts(&entries)
+    );
+
+    assert!(rename_category(&mut entries[1], "rustlang", "rust"));
+    assert!(!rename_category(&mut entries[2], "rustlang", "rust"));
+    assert_eq!(vec![("cli", 2), ("rust", 2)], term_counts(&entries));
+
+    assert!(rename_category(&mut entries[0], "cli", "rust"));
+    assert_eq!(vec![category("rust")], entries[0].categories);
+}
@@ -0,0 +1,135 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+//! The types that the content of an entry can have (RFC 4287 section
+//! 4.1.3.1), and how content of each type is carried in the feed.
+
+use anyhow::{bail, Result};
+
+/// Loosely check whether *it* looks like a MIME type: a type and a subtype,
+/// separated by a single slash, with no whitespace.
+pub fn is_mime_type(it: &str) -> bool {
+    match it.split_once('/') {
+        Some((kind, subtype)) => {
+            !kind.is_empty()
+                && !subtype.is_empty()
+                && !subtype.contains('/')
+                && !it.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+/// Make sure inline content can be carried with *content_type*: it must be
+/// text, html, xhtml, or a MIME type, but not an XML one, since those would
+/// have to be embedded as XML elements rather than text, which we can't do.
+pub fn check_content_type(content_type: Option<&str>) -> Result<()> {
+    match content_type {
+        None | Some("text") | Some("html") | Some("xhtml") => Ok(()),
+        Some(it) if is_mime_type(it) && is_xml_media_type(it) => bail!(
+            "content of type {} can't be embedded in the feed; host it elsewhere and pass --content-src instead",
+            it
+        ),
+        Some(it) if is_mime_type(it) => Ok(()),
+        Some(it) => bail!(
+            "unknown content type {}; must be text, html, xhtml, or a MIME type such as image/png",
+            it
+        ),
+    }
+}
+
+/// Whether *mime_type* names an XML media type, per RFC 3023.
+pub fn is_xml_media_type(mime_type: &str) -> bool {
+    let mime_type = mime_type.split(';').next().unwrap_or_default().trim();
+    mime_type.ends_with("+xml") || mime_type.ends_with("/xml")
+}
+
+/// Whether content of *content_type* must be base64-encoded: any MIME type
+/// that is neither text/* nor XML.
+pub fn is_base64_type(content_type: &str) -> bool {
+    is_mime_type(content_type)
+        && !content_type.starts_with("text/")
+        && !is_xml_media_type(content_type)
+}
+
+#[test]
+fn check_content_type_behavior() {
+    assert!(check_content_type(None).is_ok());
+    assert!(check_content_type(Some("xhtml")).is_ok());
+    assert!(check_content_type(Some("image/png")).is_ok());
+    assert!(check_content_type(Some("htlm")).is_err());
+    assert!(check_content_type(Some("application/atom+xml")).is_err());
+
+    assert!(is_base64_type("image/png"));
+    assert!(!is_base64_type("text/plain"));
+    assert!(!is_base64_type("html"));
+    assert!(!is_base64_type("application/xhtml+xml;charset=utf-8"));
+}
+
+/// Standard base64 (RFC 4648 section 4), with padding and no line breaks.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity((bytes.len() + 2) / 3 * 4);
+
+    for chunk in bytes.chunks(3) {
+        let word = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                out.push(ALPHABET[(word >> (18 - 6 * idx) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+/// Whether *it* is well-formed standard base64, ignoring any whitespace (such
+/// as line breaks) in it.
+pub fn is_base64(it: &str) -> bool {
+    let chars: Vec<char> = it.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    let padding = chars.iter().rev().take_while(|c| **c == '=').count();
+
+    chars.len() % 4 == 0
+        && padding <= 2
+        && chars[..chars.len() - padding]
+            .iter()
+            .all(|c| c.is_ascii_alphanumeric() || *c == '+' || *c == '/')
+}
+
+#[test]
+fn is_base64_behavior() {
+    assert!(is_base64(""));
+    assert!(is_base64("Zm9vYg=="));
+    assert!(is_base64("Zm9v\nYmE=\n"));
+    assert!(!is_base64("not base64 at all!"));
+    assert!(!is_base64("Zm9vY"));
+    assert!(!is_base64("Zg==="));
+}
+
+#[test]
+fn base64_encode_behavior() {
+    // The test vectors from RFC 4648 section 10, covering every padding length.
+    assert_eq!("", base64_encode(b""));
+    assert_eq!("Zg==", base64_encode(b"f"));
+    assert_eq!("Zm8=", base64_encode(b"fo"));
+    assert_eq!("Zm9v", base64_encode(b"foo"));
+    assert_eq!("Zm9vYg==", base64_encode(b"foob"));
+    assert_eq!("Zm9vYmE=", base64_encode(b"fooba"));
+    assert_eq!("Zm9vYmFy", base64_encode(b"foobar"));
+    assert_eq!("/+8A", base64_encode(&[0xff, 0xef, 0x00]));
+}
@@ -0,0 +1,518 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Result};
+use argh::FromArgs;
+use atom_syndication::{Entry as AtomEntry, Feed, Person, Text};
+use chrono::{DateTime, Utc};
+use log::warn;
+
+use crate::add_command::{
+    check_out_of_line_content, content_from_args, content_type_from_path, decode_content,
+    encode_inline_content, inline_or_file, inline_or_file_bytes, render_markdown,
+    summary_from_content,
+};
+use crate::kaboom_command::KaboomCommand;
+use crate::kaboom_feed::KaboomFeed;
+use crate::stringable_link::{merge_links, StringableLink};
+use crate::stringable_person::StringablePerson;
+use crate::text_construct::{changed_text, TextKind};
+use crate::Kaboom;
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand, name = "edit")]
+/// Modify an existing entry in place. Only the fields provided will be
+/// changed, and *updated_at* will be bumped to now unless told otherwise.
+pub struct EditCommand {
+    #[argh(positional)]
+    /// the URI of the entry to edit
+    id: String,
+
+    #[argh(option, short = 't')]
+    /// the new title of the entry
+    title: Option<String>,
+
+    #[argh(option)]
+    /// the type of *title*: text (the default), html, or xhtml. given without
+    /// *title*, changes the type of the existing title.
+    title_type: Option<TextKind>,
+
+    #[argh(option, short = 's')]
+    /// a new short summary of the entry
+    summary: Option<String>,
+
+    #[argh(option, short = 'S')]
+    /// path to a file containing a new short summary of the entry, or - to
+    /// read it from standard input. mutually exclusive with *summary*.
+    summary_file: Option<PathBuf>,
+
+    #[argh(option)]
+    /// the type of *summary*: text (the default), html, or xhtml. given
+    /// without *summary*, changes the type of the existing summary.
+    summary_type: Option<TextKind>,
+
+    #[argh(option)]
+    /// a new statement of the rights held in and over this entry, such as a
+    /// copyright notice
+    rights: Option<String>,
+
+    #[argh(option)]
+    /// the type of *rights*: text (the default), html, or xhtml. given without
+    /// *rights*, changes the type of the existing rights.
+    rights_type: Option<TextKind>,
+
+    #[argh(option, short = 'c')]
+    /// the new full content of the entry
+    content: Option<String>,
+
+    #[argh(option, short = 'C')]
+    /// path to a file containing the new full content of the entry, or - to
+    /// read it from standard input. mutually exclusive with *content*.
+    content_file: Option<PathBuf>,
+
+    #[argh(option)]
+    /// a URL where the full content of the entry can be found, replacing any
+    /// existing content, inline or not. requires a MIME type as
+    /// *content_type*, and a summary. mutually exclusive with *content* and
+    /// *content_file*.
+    content_src: Option<String>,
+
+    #[argh(option, short = 'T')]
+    /// the content type of *content*; must be "text", "html", "xhtml", or a
+    /// MIME type, or "markdown" if kaboom was built with Markdown support, in
+    /// which case the content is stored as rendered HTML. content of a binary
+    /// MIME type is base64-encoded, and XML MIME types need *content_src*.
+    /// ignored if neither *content*, *content_src*, nor existing content is
+    /// present. if omitted, inferred from the extension of *content_file*
+    /// where possible, or else kept from the existing content.
+    content_type: Option<String>,
+
+    #[argh(switch, short = 'P')]
+    /// if no *summary* is provided, use the first paragraph of the new
+    /// *content* as the summary. only supported for Markdown content.
+    summary_from_content: bool,
+
+    #[argh(option, short = 'L')]
+    /// the language of *content*, often a code like en-us. ignored if neither
+    /// *content* nor existing content is present.
+    content_language: Option<String>,
+
+    #[argh(option, short = 'a')]
+    /// an author of this entry, in the form "Name <email> (uri)", where the
+    /// email and uri are optional, replacing any existing authors. can be
+    /// specified multiple times.
+    author: Vec<StringablePerson>,
+
+    #[argh(option)]
+    /// a contributor to this entry, in the same form as *author*, replacing
+    /// any existing contributors. can be specified multiple times.
+    contributor: Vec<StringablePerson>,
+
+    #[argh(option, short = 'l')]
+    /// a link related to this entry, in the same form as `kaboom meta
+    /// --rel-link`. a link with the same href as an existing one replaces
+    /// it. can be provided multiple times.
+    link: Vec<StringableLink>,
+    #[argh(switch, short = 'R')]
+    /// remove all of the entry's existing links. if *link* are still provided,
+    /// they become the entry's only links.
+    remove_links: bool,
+
+    #[argh(option, short = 'd')]
+    /// the date and time, in RFC3339 format, when the entry was published
+    published_at: Option<DateTime<Utc>>,
+
+    #[argh(option, short = 'D')]
+    /// the date, in RFC3339 format, when the entry was most recently updated.
+    /// defaults to now if any other field was changed.
+    updated_at: Option<DateTime<Utc>>,
+
+    #[argh(switch, short = 'k')]
+    /// do not bump *updated_at* automatically (an explicit *updated_at* is
+    /// still honored)
+    keep_updated: bool,
+}
+
+impl EditCommand {
+    /// Change whichever fields of *entry* were given on the command line, with
+    /// *summary*, *content*, and *content_type* already read and decoded by the
+    /// caller, bumping *updated* to *now* unless told otherwise. Returns
+    /// whether anything changed.
+    fn apply_to(
+        &self,
+        entry: &mut AtomEntry,
+        summary: Option<String>,
+        derived_summary: Option<Text>,
+        content: Option<String>,
+        content_type: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Result<bool> {
+        let mut any_updates = false;
+
+        if let Some(title) = changed_text(
+            "title",
+            Some(entry.title()),
+            self.title.as_deref(),
+            self.title_type,
+        )? {
+            entry.set_title(title);
+            any_updates = true;
+        }
+
+        if let Some(summary) = changed_text(
+            "summary",
+            entry.summary(),
+            summary.as_deref(),
+            self.summary_type,
+        )? {
+            entry.set_summary(Some(summary));
+            any_updates = true;
+        } else if let Some(summary) = derived_summary {
+            if entry.summary() != Some(&summary) {
+                entry.set_summary(Some(summary));
+                any_updates = true;
+            }
+        }
+
+        if let Some(rights) = changed_text(
+            "rights",
+            entry.rights(),
+            self.rights.as_deref(),
+            self.rights_type,
+        )? {
+            entry.set_rights(Some(rights));
+            any_updates = true;
+        }
+
+        if let Some(published_at) = self.published_at {
+            if entry.published().map(|p| p.with_timezone(&Utc)) != Some(published_at) {
+                entry.set_published(Some(published_at.into()));
+                any_updates = true;
+            }
+        }
+
+        if !self.author.is_empty() {
+            let people: Vec<Person> = self.author.iter().cloned().map(Person::from).collect();
+            if entry.authors() != people.as_slice() {
+                entry.set_authors(people);
+                any_updates = true;
+            }
+        }
+
+        if !self.contributor.is_empty() {
+            let people: Vec<Person> = self.contributor.iter().cloned().map(Person::from).collect();
+            if entry.contributors() != people.as_slice() {
+                entry.set_contributors(people);
+                any_updates = true;
+            }
+        }
+
+        let links_before = entry.links().to_vec();
+        if self.remove_links {
+            entry.set_links(Vec::with_capacity(self.link.len()));
+        }
+        merge_links(&mut entry.links, &self.link);
+        any_updates |= entry.links() != links_before.as_slice();
+
+        match (&mut entry.content, &content, &self.content_src) {
+            (Some(existing), _, _) => {
+                let before = existing.clone();
+
+                // New content of either kind replaces old content of either
+                // kind, since content can't be both inline and out-of-line.
+                if let Some(content) = &content {
+                    existing.set_value(content.clone());
+                    existing.set_src(None);
+                }
+                if let Some(src) = &self.content_src {
+                    existing.set_value(None);
+                    existing.set_src(src.clone());
+                }
+                if content_type.is_some() {
+                    existing.set_content_type(content_type.clone());
+                }
+                if self.content_language.is_some() {
+                    existing.set_lang(self.content_language.clone());
+                }
+
+                if *existing != before {
+                    encode_inline_content(existing)?;
+                    any_updates = true;
+                }
+            }
+            (None, None, None) => {
+                if content_type.is_some() || self.content_language.is_some() {
+                    warn!("ignoring content-type and content-language because the entry has no content");
+                }
+            }
+            (None, _, _) => {
+                let mut new_content = content_from_args(
+                    content,
+                    self.content_src.clone(),
+                    content_type,
+                    self.content_language.clone(),
+                )?;
+                if let Some(new_content) = &mut new_content {
+                    encode_inline_content(new_content)?;
+                }
+                entry.set_content(new_content);
+                any_updates = true;
+            }
+        }
+
+        if let Some(updated_at) = self.updated_at {
+            entry.set_updated(updated_at);
+            any_updates = true;
+        } else if any_updates && !self.keep_updated {
+            entry.set_updated(now);
+        }
+
+        Ok(any_updates)
+    }
+}
+
+#[test]
+fn apply_to_behavior() {
+    use atom_syndication::{Content, Link};
+
+    let then: DateTime<Utc> = "2023-01-01T00:00:00Z".parse().unwrap();
+    let now: DateTime<Utc> = "2023-06-01T00:00:00Z".parse().unwrap();
+    let original = AtomEntry {
+        id: "urn:x:1".into(),
+        title: "Old".into(),
+        summary: Some(Text::plain("A summary")),
+        updated: then.into(),
+        authors: vec![Person {
+            name: "klardotsh".into(),
+            ..Default::default()
+        }],
+        links: vec![Link {
+            href: "https://example.com/1.html".into(),
+            ..Default::default()
+        }],
+        content: Some(Content {
+            src: Some("https://example.com/1.html".into()),
+            content_type: Some("text/html".into()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let edit =
+        |args: &[&str]| EditCommand::from_args(&["edit"], &[&["urn:x:1"], args].concat()).unwrap();
+    let apply = |edit: &EditCommand, entry: &mut AtomEntry, content: Option<&str>| {
+        edit.apply_to(entry, None, None, content.map(String::from), None, now)
+            .unwrap()
+    };
+
+    // Only the fields given change, and updated is bumped.
+    let mut entry = original.clone();
+    assert!(apply(&edit(&["-t", "New"]), &mut entry, None));
+    assert_eq!("New", entry.title().as_str());
+    assert_eq!(now, *entry.updated());
+    let mut expected = original.clone();
+    expected.set_title("New");
+    expected.set_updated(now);
+    assert_eq!(expected, entry);
+
+    // Nothing changes when the given fields already match.
+    let mut entry = original.clone();
+    assert!(!apply(
+        &edit(&["-t", "Old", "-a", "klardotsh"]),
+        &mut entry,
+        None
+    ));
+    assert_eq!(original, entry);
+
+    // -k keeps updated as it was, but an explicit -D always wins.
+    let mut entry = original.clone();
+    assert!(apply(&edit(&["-t", "New", "-k"]), &mut entry, None));
+    assert_eq!(then, *entry.updated());
+    assert!(apply(
+        &edit(&["-D", "2023-03-01T00:00:00Z"]),
+        &mut entry,
+        None
+    ));
+    assert_eq!("2023-03-01T00:00:00+00:00", entry.updated().to_rfc3339());
+
+    // New inline content replaces out-of-line content, and vice versa.
+    let mut entry = original.clone();
+    assert!(apply(&edit(&[]), &mut entry, Some("<p>hi</p>")));
+    let content = entry.content().unwrap();
+    assert_eq!((Some("<p>hi</p>"), None), (content.value(), content.src()));
+    assert!(apply(
+        &edit(&["--content-src", "https://example.com/2.html"]),
+        &mut entry,
+        None
+    ));
+    let content = entry.content().unwrap();
+    assert_eq!(
+        (None, Some("https://example.com/2.html")),
+        (content.value(), content.src())
+    );
+
+    // -R with -l leaves only the given links.
+    let mut entry = original.clone();
+    assert!(apply(
+        &edit(&["-R", "-l", "https://example.com/3.html[rel=related]"]),
+        &mut entry,
+        None
+    ));
+    assert_eq!(
+        vec![("https://example.com/3.html", "related")],
+        entry
+            .links()
+            .iter()
+            .map(|it| (it.href(), it.rel()))
+            .collect::<Vec<_>>()
+    );
+}
+
+impl KaboomCommand for EditCommand {
+    fn run(&self, top_args: &Kaboom) -> Result<()> {
+        let summary = inline_or_file(&self.summary, &self.summary_file, &self.content_file)?;
+        // Standard input has to be read before taking the lock, but how to
+        // decode it can depend on the type of the entry's existing content.
+        let content_bytes =
+            inline_or_file_bytes(&self.content, &self.content_file, &self.summary_file)?;
+
+        if content_bytes.is_some() && self.content_src.is_some() {
+            bail!("got both inline content and --content-src, pick one");
+        }
+
+        let _lock = Feed::lock(&top_args.file, top_args.wait, top_args.no_op)?;
+        let mut feed = Feed::read_from_path(&top_args.file)?;
+        let original = feed.clone();
+        let entry = feed
+            .entries
+            .iter_mut()
+            .find(|entry| entry.id() == self.id)
+            .ok_or_else(|| anyhow!("no entry with id {} exists in the feed", self.id))?;
+
+        let content_type = self
+            .content_type
+            .clone()
+            .or_else(|| {
+                self.content_file
+                    .as_deref()
+                    .and_then(content_type_from_path)
+            })
+            .or_else(|| {
+                entry
+                    .content()
+                    .and_then(|it| it.content_type())
+                    .map(String::from)
+            });
+        let content = decode_content(content_bytes, content_type.as_deref())?;
+        let derived_summary = match (&summary, &content) {
+            (None, Some(content)) if self.summary_from_content => {
+                summary_from_content(content, content_type.as_deref())?
+            }
+            _ => None,
+        };
+        let (content, content_type) = render_markdown(content, content_type)?;
+
+        let any_updates = self.apply_to(
+            entry,
+            summary,
+            derived_summary,
+            content,
+            content_type,
+            Utc::now(),
+        )?;
+
+        if !any_updates {
+            warn!("not writing anything because no changes were requested");
+            return Ok(());
+        }
+
+        check_out_of_line_content(entry)?;
+
+        feed.stamp_if_modified(&original, top_args.feed_updated, !top_args.no_generator);
+        feed.write_or_preview(&original, &top_args.file, top_args.no_op)?;
+
+        Ok(())
+    }
+}
+
+#[test]
+fn edit_content_type_behavior() {
+    use atom_syndication::Content;
+
+    let dir = std::env::temp_dir().join(format!("kaboom-edit-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("feed.xml");
+    let png = dir.join("pic.png");
+    std::fs::write(&png, [0x89, b'P', b'N', b'G', 0xff]).unwrap();
+
+    let mut feed = Feed {
+        title: "T".into(),
+        id: "urn:x".into(),
+        ..Default::default()
+    };
+    feed.entries.push(AtomEntry {
+        id: "urn:x:1".into(),
+        summary: Some(Text::plain("a picture")),
+        content: Some(Content {
+            value: Some("AAAA".into()),
+            content_type: Some("image/png".into()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    feed.write_to_path(&path).unwrap();
+
+    let path_arg = path.to_string_lossy().to_string();
+    let png_arg = png.to_string_lossy().to_string();
+    let run = |args: &[&str]| {
+        let args = [&["-f", path_arg.as_str(), "edit", "urn:x:1"], args].concat();
+        let args = Kaboom::from_args(&["kaboom"], &args).unwrap();
+        match &args.command {
+            crate::KaboomSubCommand::Edit(edit) => edit.run(&args),
+            _ => unreachable!(),
+        }
+    };
+    let content = || {
+        let feed = Feed::read_from_path(&path).unwrap();
+        let content = feed.entries()[0].content().unwrap().clone();
+        (
+            content.value().map(String::from),
+            content.content_type().map(String::from),
+        )
+    };
+
+    // Without -T, new content keeps being carried as the existing type.
+    run(&["-c", "not base64 at all!"]).unwrap();
+    assert_eq!(
+        (
+            Some("bm90IGJhc2U2NCBhdCBhbGwh".to_string()),
+            Some("image/png".to_string())
+        ),
+        content(),
+    );
+
+    run(&["-C", &png_arg]).unwrap();
+    assert_eq!(
+        (Some("iVBOR/8=".to_string()), Some("image/png".to_string())),
+        content(),
+    );
+
+    run(&["-c", "<p>hi</p>", "-T", "html"]).unwrap();
+    assert_eq!(
+        (Some("<p>hi</p>".to_string()), Some("html".to_string())),
+        content(),
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,127 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+//! Human-readable diffs between two versions of a feed, used to show what
+//! *would* change when no-op is requested.
+
+use std::path::Path;
+
+use atom_syndication::Feed;
+
+use crate::kaboom_entry::KaboomEntry;
+use crate::kaboom_feed::KaboomFeed;
+
+/// Describe everything that differs between *before* and *after*, two versions
+/// of the feed at *path*: metadata first, then entries (matched up by ID) in
+/// the order they appear in *after*, then any entries that were dropped.
+pub fn diff_feeds(before: &Feed, after: &Feed, path: &Path) -> String {
+    let mut out = Vec::new();
+
+    for (sign, line) in diff_lines(&before.as_human_text(), &after.as_human_text()) {
+        out.push(format!("{}{}", sign, line));
+    }
+
+    for entry in after.entries() {
+        match before.entries().iter().find(|it| it.id() == entry.id()) {
+            None => {
+                out.push(format!("+entry={}", entry.id()));
+                for line in entry.as_human_text().lines() {
+                    out.push(format!("+  {}", line));
+                }
+            }
+            Some(old) if old != entry => {
+                out.push(format!("~entry={}", entry.id()));
+                for (sign, line) in diff_lines(&old.as_human_text(), &entry.as_human_text()) {
+                    out.push(format!("{}  {}", sign, line));
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    for entry in before.entries() {
+        if !after.entries().iter().any(|it| it.id() == entry.id()) {
+            out.push(format!("-entry={}", entry.id()));
+            for line in entry.as_human_text().lines() {
+                out.push(format!("-  {}", line));
+            }
+        }
+    }
+
+    if out.is_empty() {
+        format!("no changes to {}\n", path.to_string_lossy())
+    } else {
+        format!(
+            "--- {path}\n+++ {path}\n{}\n",
+            out.join("\n"),
+            path = path.to_string_lossy()
+        )
+    }
+}
+
+/// A minimal line diff: return only the lines removed from *old* (signed -)
+/// and added in *new* (signed +), in document order, based on their longest
+/// common subsequence. Feed and entry dumps are short, so the quadratic table
+/// is no concern.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<(char, &'a str)> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+
+    for oidx in (0..old.len()).rev() {
+        for nidx in (0..new.len()).rev() {
+            lcs[oidx][nidx] = if old[oidx] == new[nidx] {
+                lcs[oidx + 1][nidx + 1] + 1
+            } else {
+                lcs[oidx + 1][nidx].max(lcs[oidx][nidx + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut oidx, mut nidx) = (0, 0);
+
+    while oidx < old.len() && nidx < new.len() {
+        if old[oidx] == new[nidx] {
+            oidx += 1;
+            nidx += 1;
+        } else if lcs[oidx + 1][nidx] >= lcs[oidx][nidx + 1] {
+            out.push(('-', old[oidx]));
+            oidx += 1;
+        } else {
+            out.push(('+', new[nidx]));
+            nidx += 1;
+        }
+    }
+
+    out.extend(old[oidx..].iter().map(|line| ('-', *line)));
+    out.extend(new[nidx..].iter().map(|line| ('+', *line)));
+    out
+}
+
+#[test]
+fn diff_lines_behavior() {
+    assert_eq!(
+        vec![
+            ('-', "subtitle=old"),
+            ('+', "subtitle=new"),
+            ('+', "icon=x")
+        ],
+        diff_lines(
+            "title=T\nsubtitle=old\nuri=u",
+            "title=T\nsubtitle=new\nuri=u\nicon=x"
+        ),
+    );
+    assert_eq!(Vec::<(char, &str)>::new(), diff_lines("a\nb", "a\nb"));
+    assert_eq!(vec![('-', "a"), ('-', "b")], diff_lines("a\nb", ""));
+}
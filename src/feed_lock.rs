@@ -0,0 +1,139 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+//! Advisory locking of a feed for the duration of a read-modify-write, so that
+//! two kaboom processes working on the same feed (say, a deploy hook running
+//! `kaboom add` while cron runs `kaboom prune`) take turns rather than one
+//! silently discarding the other's changes. The lock is taken on a sidecar
+//! file next to the feed, since the feed itself is replaced on every write.
+//! Reject files and archives are only ever written while holding the lock of
+//! the feed they belong to, so they need no locks of their own.
+
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use log::{debug, warn};
+
+use crate::atomic_write::resolve_symlinks;
+
+/// How long to sleep between attempts to take a lock that's already held.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An exclusive lock on a feed, released when dropped.
+#[derive(Debug)]
+pub struct FeedLock {
+    // Never read, but must be kept open: closing it releases the lock.
+    _file: File,
+    path: PathBuf,
+}
+
+impl Drop for FeedLock {
+    fn drop(&mut self) {
+        debug!("releasing lock {}", self.path.to_string_lossy());
+    }
+}
+
+/// The sidecar lock file for the feed at *feed_path*: feed.xml.lock for
+/// feed.xml, after following any symlinks, so that every path to the same feed
+/// shares one lock. The lock file is left behind after use, since removing it
+/// would race with anyone waiting on it, and it's created before the feed is
+/// read, so it's there even when the feed then turns out not to parse.
+pub fn lock_path(feed_path: &Path) -> Result<PathBuf> {
+    let target = resolve_symlinks(feed_path)?;
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    Ok(target.with_file_name(name))
+}
+
+/// Take an exclusive lock on the feed at *feed_path*, waiting up to *wait* for
+/// any other kaboom process to release it first (or not at all, if None).
+pub fn lock_feed(feed_path: &Path, wait: Option<Duration>) -> Result<FeedLock> {
+    let path = lock_path(feed_path)?;
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("could not open lock file {}", path.to_string_lossy()))?;
+    let started = Instant::now();
+    let mut warned = false;
+
+    while !try_lock(&file).with_context(|| format!("could not lock {}", path.to_string_lossy()))? {
+        let waited = started.elapsed();
+
+        match wait {
+            Some(wait) if waited < wait => {
+                if !warned {
+                    warn!(
+                        "waiting up to {} for another kaboom process to release {}",
+                        humantime::format_duration(wait),
+                        path.to_string_lossy()
+                    );
+                    warned = true;
+                }
+                std::thread::sleep(POLL_INTERVAL.min(wait - waited));
+            }
+            _ => bail!(
+                "{} is locked by another kaboom process; pass --wait to wait for it",
+                feed_path.to_string_lossy()
+            ),
+        }
+    }
+
+    debug!("took lock {}", path.to_string_lossy());
+    Ok(FeedLock { _file: file, path })
+}
+
+/// Try to take an exclusive lock on *file* without blocking, returning whether
+/// we got it.
+#[cfg(unix)]
+fn try_lock(file: &File) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: the file descriptor is valid for as long as *file* lives.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EWOULDBLOCK) => Ok(false),
+        _ => Err(err.into()),
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> Result<bool> {
+    debug!("file locking is not supported on this platform, carrying on without it");
+    Ok(true)
+}
+
+#[cfg(unix)]
+#[test]
+fn lock_feed_behavior() {
+    let dir = std::env::temp_dir().join(format!("kaboom-lock-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let feed = dir.join("feed.xml");
+
+    let lock = lock_feed(&feed, None).unwrap();
+    assert!(dir.join("feed.xml.lock").exists());
+    assert!(lock_feed(&feed, None).is_err());
+    assert!(lock_feed(&feed, Some(Duration::from_millis(150))).is_err());
+
+    drop(lock);
+    assert!(lock_feed(&feed, None).is_ok());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
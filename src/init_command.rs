@@ -0,0 +1,106 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use std::io::ErrorKind;
+
+use anyhow::{bail, Result};
+use argh::FromArgs;
+use atom_syndication::{Feed, Link as AtomLink, Person};
+
+use crate::kaboom_command::KaboomCommand;
+use crate::kaboom_feed::KaboomFeed;
+use crate::stringable_person::StringablePerson;
+use crate::text_construct::TextKind;
+use crate::Kaboom;
+
+#[derive(FromArgs, Debug)]
+/// Create a new Atom feed with no entries. Refuses to touch a file that
+/// already exists; use `kaboom meta` to change an existing feed instead.
+#[argh(subcommand, name = "init")]
+pub struct InitCommand {
+    #[argh(option, short = 't')]
+    /// a human-readable title for the feed
+    title: String,
+    #[argh(option)]
+    /// the type of *title*: text (the default), html, or xhtml
+    title_type: Option<TextKind>,
+
+    #[argh(option, short = 'u')]
+    /// a unique and permanent URI for this feed, often the URL at which it is
+    /// accessed. if it is an http(s) URL, it is also used as the feed's
+    /// rel=self link.
+    uri: String,
+
+    #[argh(option, short = 's')]
+    /// an optional string to provide a human-readable description or subtitle
+    /// for the feed
+    subtitle: Option<String>,
+    #[argh(option)]
+    /// the type of *subtitle*: text (the default), html, or xhtml
+    subtitle_type: Option<TextKind>,
+
+    #[argh(option, short = 'a')]
+    /// an author of the feed, in the form "Name <email> (uri)", where the email
+    /// and uri are optional. can be provided multiple times.
+    author: Vec<StringablePerson>,
+}
+
+impl KaboomCommand for InitCommand {
+    fn run(&self, top_args: &Kaboom) -> Result<()> {
+        let _lock = Feed::lock(&top_args.file, top_args.wait, top_args.no_op)?;
+        match std::fs::metadata(&top_args.file) {
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            _ => bail!(
+                "{} already exists; use kaboom meta to change it",
+                top_args.file.to_string_lossy()
+            ),
+        }
+
+        let original = Feed::default();
+        let mut feed = original.clone();
+
+        feed.set_title(
+            self.title_type
+                .unwrap_or_default()
+                .text("title", &self.title)?,
+        );
+        feed.set_id(self.uri.clone());
+        feed.set_subtitle(
+            self.subtitle
+                .as_deref()
+                .map(|it| self.subtitle_type.unwrap_or_default().text("subtitle", it))
+                .transpose()?,
+        );
+        feed.set_authors(
+            self.author
+                .iter()
+                .cloned()
+                .map(Person::from)
+                .collect::<Vec<Person>>(),
+        );
+
+        if self.uri.starts_with("http://") || self.uri.starts_with("https://") {
+            feed.set_links(vec![AtomLink {
+                href: self.uri.clone(),
+                rel: "self".into(),
+                mime_type: Some("application/atom+xml".into()),
+                ..Default::default()
+            }]);
+        }
+
+        feed.stamp_if_modified(&original, top_args.feed_updated, !top_args.no_generator);
+        feed.write_or_preview(&original, &top_args.file, top_args.no_op)?;
+
+        Ok(())
+    }
+}
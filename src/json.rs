@@ -0,0 +1,159 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+//! Just enough JSON to describe feeds and entries on standard output, without
+//! pulling in serde and friends for what amounts to a handful of objects.
+
+use std::fmt::{Display, Write};
+use std::str::FromStr;
+
+#[derive(Eq, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// The key=value lines kaboom has always printed
+    Human,
+    /// A single JSON document per invocation (lists become arrays)
+    Json,
+    /// One compact JSON object per line (lists become one line per item)
+    JsonLines,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self::Human
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = &'static str;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        match it {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            "jsonl" => Ok(Self::JsonLines),
+            _ => Err("unknown output format"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// Keys are kept in insertion order so output is stable between runs.
+    Object(Vec<(&'static str, JsonValue)>),
+}
+
+impl Display for JsonValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(it) => write!(f, "{}", it),
+            Self::Number(it) => write!(f, "{}", it),
+            Self::String(it) => write_escaped(f, it),
+            Self::Array(items) => {
+                f.write_char('[')?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_char(']')
+            }
+            Self::Object(pairs) => {
+                f.write_char('{')?;
+                for (idx, (key, value)) in pairs.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_escaped(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_escaped(f: &mut std::fmt::Formatter<'_>, it: &str) -> std::fmt::Result {
+    f.write_char('"')?;
+    for ch in it.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => f.write_char(ch)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl From<&str> for JsonValue {
+    fn from(it: &str) -> Self {
+        Self::String(it.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(it: String) -> Self {
+        Self::String(it)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(it: bool) -> Self {
+        Self::Bool(it)
+    }
+}
+
+impl From<u64> for JsonValue {
+    fn from(it: u64) -> Self {
+        Self::Number(it)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(it: Option<T>) -> Self {
+        it.map_or(Self::Null, Into::into)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(it: Vec<T>) -> Self {
+        Self::Array(it.into_iter().map(Into::into).collect())
+    }
+}
+
+#[test]
+fn json_value_display_behavior() {
+    let value = JsonValue::Object(vec![
+        ("title", "klardotsh's \"super\" blog\n".into()),
+        ("subtitle", JsonValue::from(None::<String>)),
+        ("length", 1024u64.into()),
+        ("links", vec!["a", "b\\c"].into()),
+        ("empty", JsonValue::Object(vec![])),
+        ("bell", "\u{7}".into()),
+    ]);
+
+    assert_eq!(
+        r#"{"title":"klardotsh's \"super\" blog\n","subtitle":null,"length":1024,"links":["a","b\\c"],"empty":{},"bell":"\u0007"}"#,
+        value.to_string(),
+    );
+}
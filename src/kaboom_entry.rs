@@ -0,0 +1,226 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use std::str::FromStr;
+
+use atom_syndication::{Entry as AtomEntry, FixedDateTime};
+use log::warn;
+
+use crate::json::JsonValue;
+use crate::stringable_category::StringableCategory;
+use crate::stringable_link::StringableLink;
+use crate::stringable_person::StringablePerson;
+use crate::text_construct::{text_type_line, text_type_name};
+
+pub trait KaboomEntry {
+    fn as_human_line(&self) -> String;
+    fn as_human_text(&self) -> String;
+    fn as_json(&self) -> JsonValue;
+    fn date(&self, order: DateOrder) -> &FixedDateTime;
+}
+
+/// Which of an entry's dates it is sorted, filtered, and pruned by.
+#[derive(Clone, Copy, Eq, Debug, PartialEq)]
+pub enum DateOrder {
+    /// The date of publication, or of most recent update if unpublished.
+    PublishedThenUpdated,
+    /// The date of most recent update, always.
+    UpdatedOnly,
+}
+
+impl Default for DateOrder {
+    fn default() -> Self {
+        Self::PublishedThenUpdated
+    }
+}
+
+impl FromStr for DateOrder {
+    type Err = &'static str;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        match it {
+            "published" => Ok(Self::PublishedThenUpdated),
+            "updated" => Ok(Self::UpdatedOnly),
+            _ => Err("unknown date order"),
+        }
+    }
+}
+
+/// Warn about any of *entries* that had to be dated by their fallback date
+/// under *order*, since they may not land where the user expects.
+pub fn warn_missing_dates<'a>(entries: impl IntoIterator<Item = &'a AtomEntry>, order: DateOrder) {
+    if order != DateOrder::PublishedThenUpdated {
+        return;
+    }
+
+    let undated: Vec<&str> = entries
+        .into_iter()
+        .filter(|it| it.published().is_none())
+        .map(|it| it.id())
+        .collect();
+
+    if !undated.is_empty() {
+        warn!(
+            "dating {} entries by when they were updated because they have no published date: {}",
+            undated.len(),
+            undated.join(", ")
+        );
+    }
+}
+
+impl KaboomEntry for AtomEntry {
+    /// A single-line summary of the entry, suitable for `kaboom list`.
+    fn as_human_line(&self) -> String {
+        format!(
+            "id={}\ttitle={}\tpublished_at={}\tupdated_at={}",
+            self.id(),
+            self.title().as_str(),
+            self.published().map_or("".into(), |p| p.to_string()),
+            self.updated(),
+        )
+    }
+
+    /// Everything we know about the entry, suitable for `kaboom show`. The
+    /// content, if any, is always dumped last, since it's likely to span
+    /// multiple lines.
+    fn as_human_text(&self) -> String {
+        let mut lines = vec![
+            format!("id={}", self.id()),
+            format!("title={}", self.title().as_str()),
+        ];
+        lines.extend(text_type_line("title", self.title()));
+
+        if let Some(published) = self.published() {
+            lines.push(format!("published_at={}", published));
+        }
+
+        lines.push(format!("updated_at={}", self.updated()));
+
+        for author in self.authors() {
+            lines.push(format!("author={}", StringablePerson::from(author)));
+        }
+
+        for contributor in self.contributors() {
+            lines.push(format!(
+                "contributor={}",
+                StringablePerson::from(contributor)
+            ));
+        }
+
+        for link in self.links() {
+            lines.push(format!("link={}", StringableLink::from(link)));
+        }
+
+        for category in self.categories() {
+            lines.push(format!("category={}", StringableCategory::from(category)));
+        }
+
+        if let Some(summary) = self.summary() {
+            lines.push(format!("summary={}", summary.as_str()));
+            lines.extend(text_type_line("summary", summary));
+        }
+
+        if let Some(rights) = self.rights() {
+            lines.push(format!("rights={}", rights.as_str()));
+            lines.extend(text_type_line("rights", rights));
+        }
+
+        if let Some(content) = self.content() {
+            if let Some(content_type) = content.content_type() {
+                lines.push(format!("content_type={}", content_type));
+            }
+            if let Some(lang) = content.lang() {
+                lines.push(format!("content_language={}", lang));
+            }
+            if let Some(src) = content.src() {
+                lines.push(format!("content_src={}", src));
+            }
+            if let Some(value) = content.value() {
+                lines.push(format!("content={}", value));
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    fn date(&self, order: DateOrder) -> &FixedDateTime {
+        match order {
+            DateOrder::PublishedThenUpdated => self.published().unwrap_or_else(|| self.updated()),
+            DateOrder::UpdatedOnly => self.updated(),
+        }
+    }
+
+    fn as_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            ("id", self.id().into()),
+            ("title", self.title().as_str().into()),
+            ("title_type", text_type_name(self.title()).into()),
+            (
+                "published_at",
+                self.published().map(|p| p.to_rfc3339()).into(),
+            ),
+            ("updated_at", self.updated().to_rfc3339().into()),
+            (
+                "authors",
+                JsonValue::Array(
+                    self.authors()
+                        .iter()
+                        .map(|it| StringablePerson::from(it).as_json())
+                        .collect(),
+                ),
+            ),
+            (
+                "contributors",
+                JsonValue::Array(
+                    self.contributors()
+                        .iter()
+                        .map(|it| StringablePerson::from(it).as_json())
+                        .collect(),
+                ),
+            ),
+            (
+                "links",
+                JsonValue::Array(
+                    self.links()
+                        .iter()
+                        .map(|it| StringableLink::from(it).as_json())
+                        .collect(),
+                ),
+            ),
+            (
+                "categories",
+                JsonValue::Array(
+                    self.categories()
+                        .iter()
+                        .map(|it| StringableCategory::from(it).as_json())
+                        .collect(),
+                ),
+            ),
+            ("summary", self.summary().map(|s| s.as_str()).into()),
+            ("summary_type", self.summary().map(text_type_name).into()),
+            ("rights", self.rights().map(|it| it.as_str()).into()),
+            ("rights_type", self.rights().map(text_type_name).into()),
+            (
+                "content",
+                self.content().map_or(JsonValue::Null, |content| {
+                    JsonValue::Object(vec![
+                        ("type", content.content_type().into()),
+                        ("language", content.lang().into()),
+                        ("src", content.src().into()),
+                        ("value", content.value().into()),
+                    ])
+                }),
+            ),
+        ])
+    }
+}
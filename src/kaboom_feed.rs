@@ -11,21 +11,286 @@
 // OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
 // PERFORMANCE OF THIS SOFTWARE.
 
-use std::fs::File;
-use std::io::BufReader;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use anyhow::Result;
-use atom_syndication::Feed;
-use log::debug;
+use anyhow::{anyhow, Context, Result};
+use atom_syndication::extension::Extension;
+use atom_syndication::{Entry as AtomEntry, Feed, Generator as AtomGenerator};
+use chrono::{DateTime, FixedOffset, Utc};
+use log::{debug, warn};
 
+use crate::atomic_write::write_atomically;
+use crate::feed_diff::diff_feeds;
+use crate::feed_lock::{lock_feed, FeedLock};
+use crate::json::JsonValue;
+use crate::kaboom_entry::{DateOrder, KaboomEntry};
+use crate::stringable_category::StringableCategory;
 use crate::stringable_link::StringableLink;
+use crate::stringable_person::StringablePerson;
+use crate::text_construct::{text_type_line, text_type_name};
 
 pub trait KaboomFeed {
     fn as_human_text(&self) -> String;
+    fn as_json(&self) -> JsonValue;
     fn links_as_human_text(&self) -> Option<String>;
+    fn lock(
+        path: &Path,
+        wait: Option<humantime::Duration>,
+        no_op: bool,
+    ) -> Result<Option<FeedLock>>;
     fn read_from_path(path: &Path) -> Result<Feed>;
+    fn stamp_if_modified(&mut self, original: &Feed, updated: FeedUpdated, generator: bool);
+    fn stamp_if_modified_at(
+        &mut self,
+        original: &Feed,
+        updated: FeedUpdated,
+        generator: bool,
+        now: DateTime<FixedOffset>,
+    );
     fn write_to_path(&self, path: &Path) -> Result<()>;
+    fn write_or_preview(&self, original: &Feed, path: &Path, no_op: bool) -> Result<()>;
+    fn write_rejects_to_path(
+        &self,
+        rejected: Vec<AtomEntry>,
+        path: &Path,
+        order: DateOrder,
+        no_op: bool,
+    ) -> Result<()>;
+}
+
+/// What a feed's own *updated* is set to when a command modifies it.
+#[derive(Clone, Copy, Eq, Debug, PartialEq)]
+pub enum FeedUpdated {
+    /// The most recent *updated* of any entry in the feed, or now, if that
+    /// wouldn't move the feed's *updated* forward (say, because the newest
+    /// entry was just removed, or only metadata changed).
+    NewestEntry,
+    /// The moment the command ran.
+    Now,
+}
+
+impl Default for FeedUpdated {
+    fn default() -> Self {
+        Self::NewestEntry
+    }
+}
+
+impl FromStr for FeedUpdated {
+    type Err = &'static str;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        match it {
+            "newest-entry" => Ok(Self::NewestEntry),
+            "now" => Ok(Self::Now),
+            _ => Err("unknown feed updated source"),
+        }
+    }
+}
+
+/// Find where in *xml* the first syntax error is, as a 1-based line and column,
+/// along with a description of the error. Returns None if *xml* is well-formed
+/// as far as quick-xml can tell, in which case whatever is wrong with it must
+/// be wrong at the Atom level instead.
+pub fn xml_error_position(xml: &[u8]) -> Option<(usize, usize, String)> {
+    let mut reader = quick_xml::Reader::from_reader(xml);
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(quick_xml::events::Event::Eof) => return None,
+            Ok(_) => buf.clear(),
+            Err(err) => {
+                let pos = reader.buffer_position().min(xml.len());
+                let line_start = xml[..pos]
+                    .iter()
+                    .rposition(|it| *it == b'\n')
+                    .map_or(0, |idx| idx + 1);
+                let line = xml[..pos].iter().filter(|it| **it == b'\n').count() + 1;
+                let column = String::from_utf8_lossy(&xml[line_start..pos])
+                    .chars()
+                    .count()
+                    + 1;
+                return Some((line, column, err.to_string()));
+            }
+        }
+    }
+}
+
+#[test]
+fn xml_error_position_behavior() {
+    assert_eq!(
+        None,
+        xml_error_position(b"<feed>\n  <title>ok</title>\n</feed>")
+    );
+
+    let (line, column, _) = xml_error_position(b"<feed>\n  <title>ok</titel>\n</feed>").unwrap();
+    assert_eq!((2, 14), (line, column));
+}
+
+/// The reject file used when none is explicitly requested: *feed_path* with
+/// any .xml extension removed, and then ".rej.xml" added.
+pub fn default_reject_path(feed_path: &Path) -> PathBuf {
+    let mut rej_path = PathBuf::from(feed_path);
+
+    if let Some("xml") = rej_path.extension().and_then(|e| e.to_str()) {
+        rej_path.set_extension("rej.xml");
+    } else {
+        let mut name = rej_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".rej.xml");
+        rej_path.set_file_name(name);
+    }
+
+    rej_path
+}
+
+#[test]
+fn default_reject_path_behavior() {
+    assert_eq!(
+        PathBuf::from("blog/feed.rej.xml"),
+        default_reject_path(Path::new("blog/feed.xml")),
+    );
+    assert_eq!(
+        PathBuf::from("blog/feed.atom.rej.xml"),
+        default_reject_path(Path::new("blog/feed.atom")),
+    );
+    assert_eq!(
+        PathBuf::from("feed.rej.xml"),
+        default_reject_path(Path::new("feed"))
+    );
+}
+
+/// Combine two sets of entries, deduplicating by ID (keeping whichever version
+/// was updated most recently, preferring *incoming* on ties), and sorting the
+/// result newest-first by *order*.
+pub fn merge_entries(
+    existing: Vec<AtomEntry>,
+    incoming: Vec<AtomEntry>,
+    order: DateOrder,
+) -> Vec<AtomEntry> {
+    let mut merged: Vec<AtomEntry> = Vec::with_capacity(existing.len() + incoming.len());
+
+    for entry in incoming.into_iter().chain(existing) {
+        match merged.iter_mut().find(|it| it.id() == entry.id()) {
+            Some(kept) if kept.updated() < entry.updated() => *kept = entry,
+            Some(_) => {}
+            None => merged.push(entry),
+        }
+    }
+
+    merged.sort_by_key(|it| *it.date(order));
+    merged.reverse();
+    merged
+}
+
+#[test]
+fn merge_entries_behavior() {
+    let entry = |id: &str, published: Option<&str>, updated: &str, title: &str| AtomEntry {
+        id: id.into(),
+        title: title.into(),
+        published: published.map(|p| p.parse().unwrap()),
+        updated: updated.parse().unwrap(),
+        ..Default::default()
+    };
+
+    let merged = merge_entries(
+        vec![
+            entry(
+                "a",
+                Some("2023-01-01T00:00:00Z"),
+                "2023-01-01T00:00:00Z",
+                "old a",
+            ),
+            entry(
+                "b",
+                Some("2023-02-01T00:00:00Z"),
+                "2023-03-01T00:00:00Z",
+                "newer b",
+            ),
+        ],
+        vec![
+            entry(
+                "b",
+                Some("2023-02-01T00:00:00Z"),
+                "2023-02-01T00:00:00Z",
+                "older b",
+            ),
+            entry(
+                "a",
+                Some("2023-01-01T00:00:00Z"),
+                "2023-01-01T00:00:00Z",
+                "new a",
+            ),
+            entry("c", None, "2023-01-15T00:00:00Z", "c"),
+        ],
+        DateOrder::PublishedThenUpdated,
+    );
+
+    assert_eq!(
+        vec!["newer b", "c", "new a"],
+        merged
+            .iter()
+            .map(|it| it.title().as_str())
+            .collect::<Vec<&str>>(),
+    );
+}
+
+/// The XML namespace for kaboom's own bookkeeping elements. These only ever
+/// appear in reject files, never in a live feed. This must never change, or
+/// existing reject files will stop being understood.
+pub const KABOOM_NAMESPACE: &str = "https://sr.ht/~klardotsh/kaboom";
+
+/// Record on *entry* that it was sent to a reject file at *at*, so that `kaboom
+/// restore` can later find everything rejected in one go.
+pub fn mark_rejected(entry: &mut AtomEntry, at: DateTime<Utc>) {
+    entry.extensions.entry("kaboom".into()).or_default().insert(
+        "rejected".into(),
+        vec![Extension {
+            name: "kaboom:rejected".into(),
+            value: Some(at.to_rfc3339()),
+            ..Default::default()
+        }],
+    );
+}
+
+/// When *entry* was last sent to a reject file, if that was recorded.
+pub fn rejected_at(entry: &AtomEntry) -> Option<DateTime<FixedOffset>> {
+    let value = entry
+        .extensions
+        .get("kaboom")?
+        .get("rejected")?
+        .first()?
+        .value()?;
+    DateTime::parse_from_rfc3339(value).ok()
+}
+
+/// Strip the bookkeeping added by [mark_rejected] from *entry*, for when it
+/// goes back into a live feed.
+pub fn unmark_rejected(entry: &mut AtomEntry) {
+    if let Some(ext) = entry.extensions.get_mut("kaboom") {
+        ext.remove("rejected");
+
+        if ext.is_empty() {
+            entry.extensions.remove("kaboom");
+        }
+    }
+}
+
+#[test]
+fn mark_rejected_behavior() {
+    let mut entry = AtomEntry::default();
+    assert_eq!(None, rejected_at(&entry));
+
+    let at: DateTime<Utc> = "2023-07-16T01:23:52Z".parse().unwrap();
+    mark_rejected(&mut entry, at);
+    assert_eq!(
+        Some(at),
+        rejected_at(&entry).map(|it| it.with_timezone(&Utc))
+    );
+
+    unmark_rejected(&mut entry);
+    assert_eq!(None, rejected_at(&entry));
+    assert!(entry.extensions().is_empty());
 }
 
 impl KaboomFeed for Feed {
@@ -35,19 +300,80 @@ impl KaboomFeed for Feed {
     #[allow(clippy::to_string_in_format_args)]
     fn as_human_text(&self) -> String {
         format!(
-            "title={}{}\nuri={}\nupdated_at={}{}{}{}",
+            "title={}{}{}{}\nuri={}\nupdated_at={}{}{}{}{}{}{}",
             self.title().to_string(),
+            text_type_line("title", self.title()).map_or("".into(), |it| format!("\n{}", it)),
             self.subtitle()
                 .map_or("".into(), |st| format!("\nsubtitle={}", st.to_string())),
+            self.subtitle()
+                .and_then(|st| text_type_line("subtitle", st))
+                .map_or("".into(), |it| format!("\n{}", it)),
             self.id(),
             self.updated(),
+            self.authors()
+                .iter()
+                .map(|it| format!("\nauthor={}", StringablePerson::from(it)))
+                .collect::<String>(),
             self.icon().map_or("".into(), |st| format!("\nicon={}", st)),
             self.logo().map_or("".into(), |st| format!("\nlogo={}", st)),
+            self.rights().map_or("".into(), |rights| {
+                format!(
+                    "\nrights={}{}",
+                    rights.as_str(),
+                    text_type_line("rights", rights).map_or("".into(), |it| format!("\n{}", it)),
+                )
+            }),
+            self.categories()
+                .iter()
+                .map(|it| format!("\ncategory={}", StringableCategory::from(it)))
+                .collect::<String>(),
             self.links_as_human_text()
                 .map_or("".into(), |joined| format!("\n{}", joined)),
         )
     }
 
+    fn as_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            ("title", self.title().as_str().into()),
+            ("title_type", text_type_name(self.title()).into()),
+            ("subtitle", self.subtitle().map(|st| st.as_str()).into()),
+            ("subtitle_type", self.subtitle().map(text_type_name).into()),
+            ("uri", self.id().into()),
+            ("updated_at", self.updated().to_rfc3339().into()),
+            (
+                "authors",
+                JsonValue::Array(
+                    self.authors()
+                        .iter()
+                        .map(|it| StringablePerson::from(it).as_json())
+                        .collect(),
+                ),
+            ),
+            ("icon", self.icon().into()),
+            ("logo", self.logo().into()),
+            ("rights", self.rights().map(|it| it.as_str()).into()),
+            ("rights_type", self.rights().map(text_type_name).into()),
+            (
+                "categories",
+                JsonValue::Array(
+                    self.categories()
+                        .iter()
+                        .map(|it| StringableCategory::from(it).as_json())
+                        .collect(),
+                ),
+            ),
+            (
+                "links",
+                JsonValue::Array(
+                    self.links()
+                        .iter()
+                        .map(|it| StringableLink::from(it).as_json())
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+
     fn links_as_human_text(&self) -> Option<String> {
         let links = self.links();
 
@@ -63,39 +389,164 @@ impl KaboomFeed for Feed {
             .into()
     }
 
+    /// Take an exclusive lock on the feed at *path* (and, by extension, its
+    /// reject file and archives), to be held for the whole of a
+    /// read-modify-write cycle. With *no_op*, nothing will be written, so no
+    /// lock is taken and no lock file is created: every write replaces the
+    /// feed atomically, so reading it unlocked is safe.
+    fn lock(
+        path: &Path,
+        wait: Option<humantime::Duration>,
+        no_op: bool,
+    ) -> Result<Option<FeedLock>> {
+        if no_op {
+            debug!("not locking {} for a dry run", path.to_string_lossy());
+            return Ok(None);
+        }
+
+        lock_feed(path, wait.map(Into::into)).map(Some)
+    }
+
+    /// Read and parse the feed at *path*, pointing at the line and column of
+    /// the problem if it isn't well-formed XML.
     fn read_from_path(path: &Path) -> Result<Feed> {
-        let file = File::open(path)?;
-        Ok(Feed::read_from(BufReader::new(file))?)
+        let xml = std::fs::read(path)
+            .with_context(|| format!("could not read {}", path.to_string_lossy()))?;
+
+        Feed::read_from(&xml[..]).map_err(|err| match xml_error_position(&xml) {
+            Some((line, column, xml_err)) => anyhow!(
+                "could not parse {} at line {}, column {}: {}",
+                path.to_string_lossy(),
+                line,
+                column,
+                xml_err
+            ),
+            None => anyhow!("could not parse {}: {}", path.to_string_lossy(), err),
+        })
+    }
+
+    /// If this feed differs in any way from *original* (the feed as it was read
+    /// from disk), bump its *updated* as requested, and unless *generator* is
+    /// false, record this version of kaboom as its generator.
+    fn stamp_if_modified(&mut self, original: &Feed, updated: FeedUpdated, generator: bool) {
+        self.stamp_if_modified_at(original, updated, generator, Utc::now().into());
+    }
+
+    /// Like [KaboomFeed::stamp_if_modified], but with a fixed idea of *now*,
+    /// for when the stamped feed must come out the same every time.
+    fn stamp_if_modified_at(
+        &mut self,
+        original: &Feed,
+        updated: FeedUpdated,
+        generator: bool,
+        now: DateTime<FixedOffset>,
+    ) {
+        if self == original {
+            return;
+        }
+
+        let newest_entry = self.entries().iter().map(|it| *it.updated()).max();
+        self.set_updated(match (updated, newest_entry) {
+            (FeedUpdated::NewestEntry, Some(newest)) if &newest > original.updated() => newest,
+            _ => now,
+        });
+
+        if generator {
+            self.set_generator(AtomGenerator {
+                value: crate::APP_NAME.to_string(),
+                uri: Some(crate::APP_HOMEPAGE.into()),
+                version: Some(crate::VERSION.to_string()),
+            });
+        }
     }
 
+    /// Replace the file at *path* with this feed, without ever leaving a
+    /// half-written file behind (see [write_atomically]).
     fn write_to_path(&self, path: &Path) -> Result<()> {
-        let temp_path = {
-            let mut new_path = PathBuf::from(path);
+        write_atomically(path, |out| {
+            self.write_to(out)?;
+            Ok(())
+        })
+        .with_context(|| format!("could not write {}", path.to_string_lossy()))
+    }
 
-            if let Some(ext) = path.extension() {
-                new_path.set_extension(format!("{}.kaboom", ext.to_string_lossy()));
-            } else {
-                new_path.set_extension("xml.kaboom");
-            }
+    /// Write this feed to *path*, unless *no_op* was requested, in which case
+    /// print how it differs from *original* (the feed as it was read from
+    /// disk) instead.
+    fn write_or_preview(&self, original: &Feed, path: &Path, no_op: bool) -> Result<()> {
+        if no_op {
+            warn!(
+                "not writing {} to disk because no-op was requested",
+                path.to_string_lossy()
+            );
+            print!("{}", diff_feeds(original, self, path));
+            Ok(())
+        } else {
+            self.write_to_path(path)
+        }
+    }
 
-            new_path
+    /// Add *rejected* to the reject file at *path*, sharing all metadata from
+    /// this feed. Entries already archived there are kept, so the reject file
+    /// only ever grows: if an entry is rejected again, whichever version was
+    /// updated most recently wins. Every entry in *rejected* is stamped with
+    /// the time it was rejected (see [mark_rejected]).
+    fn write_rejects_to_path(
+        &self,
+        mut rejected: Vec<AtomEntry>,
+        path: &Path,
+        order: DateOrder,
+        no_op: bool,
+    ) -> Result<()> {
+        let original = if path.exists() {
+            Feed::read_from_path(path).with_context(|| {
+                format!(
+                    "refusing to overwrite unreadable reject file {}",
+                    path.to_string_lossy()
+                )
+            })?
+        } else {
+            Feed::default()
         };
-        let temp_path_cloned = temp_path.clone();
+        let now = Utc::now();
+        for entry in rejected.iter_mut() {
+            mark_rejected(entry, now);
+        }
 
-        let mut file = File::create(&temp_path)?;
-        debug!(
-            "writing feed to temp file {}",
-            &temp_path_cloned.to_string_lossy()
-        );
-        self.write_to(&mut file)?;
+        let mut rej_feed = self.clone();
+        rej_feed
+            .namespaces
+            .insert("kaboom".into(), KABOOM_NAMESPACE.into());
+        rej_feed.set_entries(merge_entries(original.entries().to_vec(), rejected, order));
+        rej_feed.write_or_preview(&original, path, no_op)
+    }
+}
 
-        debug!(
-            "renaming temp file {} to final path {}",
-            &temp_path_cloned.to_string_lossy(),
-            &path.to_string_lossy(),
-        );
-        std::fs::rename(&temp_path, path)?;
+#[test]
+fn stamp_if_modified_behavior() {
+    let entry = |id: &str, updated: &str| AtomEntry {
+        id: id.into(),
+        updated: updated.parse().unwrap(),
+        ..Default::default()
+    };
+    let original = Feed {
+        updated: "2023-02-01T00:00:00Z".parse().unwrap(),
+        entries: vec![entry("a", "2023-01-01T00:00:00Z")],
+        ..Default::default()
+    };
 
-        Ok(())
-    }
+    let mut feed = original.clone();
+    feed.stamp_if_modified(&original, FeedUpdated::NewestEntry, true);
+    assert_eq!(original, feed);
+
+    feed.entries.push(entry("b", "2023-03-01T00:00:00Z"));
+    feed.stamp_if_modified(&original, FeedUpdated::NewestEntry, false);
+    assert_eq!("2023-03-01T00:00:00+00:00", feed.updated().to_rfc3339());
+    assert_eq!(None, feed.generator());
+
+    let mut feed = original.clone();
+    feed.entries.clear();
+    feed.stamp_if_modified(&original, FeedUpdated::NewestEntry, true);
+    assert!(feed.updated() > original.updated());
+    assert_eq!(Some(crate::APP_NAME), feed.generator().map(|it| it.value()));
 }
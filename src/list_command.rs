@@ -0,0 +1,232 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use argh::FromArgs;
+use atom_syndication::{Entry as AtomEntry, Feed};
+use chrono::{DateTime, Utc};
+
+use crate::json::{JsonValue, OutputFormat};
+use crate::kaboom_command::KaboomCommand;
+use crate::kaboom_entry::{warn_missing_dates, DateOrder, KaboomEntry};
+use crate::kaboom_feed::KaboomFeed;
+use crate::Kaboom;
+
+#[derive(Eq, Debug, PartialEq)]
+pub enum ListSort {
+    FeedOrder,
+    Published,
+    Updated,
+    Title,
+    Id,
+}
+
+impl Default for ListSort {
+    fn default() -> Self {
+        Self::FeedOrder
+    }
+}
+
+impl FromStr for ListSort {
+    type Err = &'static str;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        match it {
+            "feed" => Ok(Self::FeedOrder),
+            "published" => Ok(Self::Published),
+            "updated" => Ok(Self::Updated),
+            "title" => Ok(Self::Title),
+            "id" => Ok(Self::Id),
+            _ => Err("unknown sort order"),
+        }
+    }
+}
+
+#[derive(FromArgs, Debug)]
+/// List the entries in the feed, one per line.
+#[argh(subcommand, name = "list")]
+pub struct ListCommand {
+    #[argh(option, short = 's', default = "ListSort::default()")]
+    /// order in which to list entries: feed, for the order they appear in the
+    /// file, published, for most recently published first (dated as picked
+    /// by the top-level *date_order*), updated, for most recently updated
+    /// first, title, or id
+    sort: ListSort,
+
+    #[argh(switch, short = 'r')]
+    /// reverse the order described by *sort*
+    reverse: bool,
+
+    #[argh(option, short = 'd')]
+    /// only list entries dated at or after this date and time, in RFC3339
+    /// format. entries are dated by their date of most recent update if
+    /// *sort* is updated, and as picked by the top-level *date_order*
+    /// otherwise.
+    since: Option<DateTime<Utc>>,
+
+    #[argh(option, short = 'D')]
+    /// only list entries dated at or before this date and time, in RFC3339
+    /// format. see *since* for which date is used.
+    until: Option<DateTime<Utc>>,
+}
+
+impl KaboomCommand for ListCommand {
+    fn run(&self, top_args: &Kaboom) -> Result<()> {
+        let feed = Feed::read_from_path(&top_args.file)?;
+
+        let selected = self.select(feed.entries(), top_args.date_order);
+
+        match top_args.format {
+            OutputFormat::Human => {
+                for entry in selected {
+                    println!("{}", entry.as_human_line());
+                }
+            }
+            OutputFormat::Json => println!(
+                "{}",
+                JsonValue::Array(selected.iter().map(|entry| entry.as_json()).collect())
+            ),
+            OutputFormat::JsonLines => {
+                for entry in selected {
+                    println!("{}", entry.as_json());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ListCommand {
+    /// Which date entries are filtered and sorted by: always the date of most
+    /// recent update if that's what we're sorting by, and *order* otherwise.
+    fn date_order(&self, order: DateOrder) -> DateOrder {
+        match self.sort {
+            ListSort::Updated => DateOrder::UpdatedOnly,
+            _ => order,
+        }
+    }
+
+    /// Filter *entries* by the requested date range, and sort what remains.
+    fn select<'a>(&self, entries: &'a [AtomEntry], order: DateOrder) -> Vec<&'a AtomEntry> {
+        let order = self.date_order(order);
+        if self.sort == ListSort::Published || self.since.is_some() || self.until.is_some() {
+            warn_missing_dates(entries, order);
+        }
+
+        let mut selected: Vec<&AtomEntry> = entries
+            .iter()
+            .filter(|entry| {
+                if self.since.is_none() && self.until.is_none() {
+                    return true;
+                }
+
+                let date = entry.date(order);
+                self.since.map_or(true, |since| date >= &since)
+                    && self.until.map_or(true, |until| date <= &until)
+            })
+            .collect();
+
+        match self.sort {
+            ListSort::FeedOrder => {}
+            ListSort::Published | ListSort::Updated => {
+                selected.sort_by_key(|it| it.date(order));
+                selected.reverse();
+            }
+            ListSort::Title => selected.sort_by_key(|it| it.title().as_str()),
+            ListSort::Id => selected.sort_by_key(|it| it.id()),
+        }
+
+        if self.reverse {
+            selected.reverse();
+        }
+
+        selected
+    }
+}
+
+#[test]
+fn select_behavior() {
+    let entry = |id: &str, title: &str, published: Option<&str>, updated: &str| AtomEntry {
+        id: id.into(),
+        title: title.into(),
+        published: published.map(|p| p.parse().unwrap()),
+        updated: updated.parse().unwrap(),
+        ..Default::default()
+    };
+    let entries = vec![
+        entry(
+            "a",
+            "Zed",
+            Some("2023-01-01T00:00:00Z"),
+            "2023-04-01T00:00:00Z",
+        ),
+        entry("b", "Alpha", None, "2023-02-01T00:00:00Z"),
+        entry(
+            "c",
+            "Mid",
+            Some("2023-03-01T00:00:00Z"),
+            "2023-03-01T00:00:00Z",
+        ),
+    ];
+    let select = |args: &[&str], order: DateOrder| {
+        ListCommand::from_args(&["list"], args)
+            .unwrap()
+            .select(&entries, order)
+            .into_iter()
+            .map(|it| it.id())
+            .collect::<Vec<&str>>()
+    };
+    let published = DateOrder::PublishedThenUpdated;
+    let updated = DateOrder::UpdatedOnly;
+
+    assert_eq!(vec!["a", "b", "c"], select(&[], published));
+    assert_eq!(vec!["c", "b", "a"], select(&["-r"], published));
+    assert_eq!(vec!["b", "c", "a"], select(&["-s", "title"], published));
+    assert_eq!(
+        vec!["a", "c", "b"],
+        select(&["-s", "title", "-r"], published)
+    );
+    assert_eq!(vec!["a", "b", "c"], select(&["-s", "id"], published));
+
+    // Sorting by publication uses the top-level date order, while sorting by
+    // update always uses the date of most recent update.
+    assert_eq!(vec!["c", "b", "a"], select(&["-s", "published"], published));
+    assert_eq!(vec!["a", "c", "b"], select(&["-s", "published"], updated));
+    assert_eq!(vec!["a", "c", "b"], select(&["-s", "updated"], published));
+    assert_eq!(
+        vec!["a", "b", "c"],
+        select(&["-s", "published", "-r"], published)
+    );
+
+    // The same goes for filtering, which keeps the requested order.
+    let since = "2023-02-01T00:00:00Z";
+    let until = "2023-03-15T00:00:00Z";
+    assert_eq!(vec!["b", "c"], select(&["-d", since], published));
+    assert_eq!(vec!["a", "b"], select(&["-D", since], published));
+    assert_eq!(vec!["b"], select(&["-D", since], updated));
+    assert_eq!(
+        vec!["b", "c"],
+        select(&["-d", since, "-D", until], published)
+    );
+    assert_eq!(
+        vec!["a"],
+        select(&["-s", "updated", "-d", until], published)
+    );
+    assert_eq!(
+        vec!["c", "b"],
+        select(&["-s", "published", "-d", since, "-D", until], published)
+    );
+}
@@ -12,11 +12,31 @@
 // PERFORMANCE OF THIS SOFTWARE.
 
 mod add_command;
+mod archive;
+mod atomic_write;
+mod category_command;
+mod content_type;
+mod edit_command;
+mod feed_diff;
+mod feed_lock;
+mod init_command;
+mod json;
 mod kaboom_command;
+mod kaboom_entry;
 mod kaboom_feed;
+mod list_command;
+#[cfg(feature = "markdown")]
+mod markdown;
 mod meta_command;
 mod prune_command;
+mod restore_command;
+mod rm_command;
+mod show_command;
+mod stringable_category;
 mod stringable_link;
+mod stringable_person;
+mod text_construct;
+mod validate_command;
 
 use std::path::PathBuf;
 
@@ -25,9 +45,20 @@ use argh::FromArgs;
 use env_logger::Env;
 
 use add_command::AddCommand;
+use category_command::CategoryCommand;
+use edit_command::EditCommand;
+use init_command::InitCommand;
+use json::OutputFormat;
 use kaboom_command::KaboomCommand;
+use kaboom_entry::DateOrder;
+use kaboom_feed::FeedUpdated;
+use list_command::ListCommand;
 use meta_command::MetaCommand;
 use prune_command::PruneCommand;
+use restore_command::RestoreCommand;
+use rm_command::RmCommand;
+use show_command::ShowCommand;
+use validate_command::ValidateCommand;
 
 pub const APP_HOMEPAGE: &str = env!("CARGO_PKG_HOMEPAGE");
 pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
@@ -44,16 +75,55 @@ pub struct Kaboom {
     file: PathBuf,
 
     #[argh(switch, short = 'n')]
-    /// do not write anything to disk, but still show what *would* change
+    /// do not write anything to disk, but still show a diff of what *would*
+    /// change
     no_op: bool,
+
+    #[argh(option, default = "OutputFormat::default()")]
+    /// format of anything printed to standard output: human, for key=value
+    /// lines, json, for a single JSON document, or jsonl, for one JSON object
+    /// per line
+    format: OutputFormat,
+
+    #[argh(option, default = "DateOrder::default()")]
+    /// which date entries are sorted, filtered, and pruned by: published, for
+    /// date of publication (or of most recent update, for entries that were
+    /// never published), or updated, for date of most recent update only
+    date_order: DateOrder,
+
+    #[argh(option, default = "FeedUpdated::default()")]
+    /// what the feed's own updated date is set to whenever a command changes
+    /// the feed: newest-entry, for the most recent update of any entry in it,
+    /// or now
+    feed_updated: FeedUpdated,
+
+    #[argh(switch, short = 'G')]
+    /// do not insert or update the generator block in the feed when changing
+    /// it (the block discloses within the feed that kaboom was used to
+    /// generate it)
+    no_generator: bool,
+
+    #[argh(option, short = 'w')]
+    /// if another kaboom process is already changing the feed, wait up to this
+    /// long (for example, 30s or 2m) for it to finish, rather than giving up
+    /// immediately
+    wait: Option<humantime::Duration>,
 }
 
 #[derive(FromArgs, Debug)]
 #[argh(subcommand)]
 enum KaboomSubCommand {
     Add(AddCommand),
+    Category(CategoryCommand),
+    Edit(EditCommand),
+    Init(InitCommand),
+    List(ListCommand),
     Meta(MetaCommand),
     Prune(PruneCommand),
+    Restore(RestoreCommand),
+    Rm(RmCommand),
+    Show(ShowCommand),
+    Validate(ValidateCommand),
     Version(KaboomVersion),
 }
 
@@ -73,7 +143,15 @@ fn main() -> Result<()> {
             Ok(())
         }
         KaboomSubCommand::Add(add) => add.run(&args),
+        KaboomSubCommand::Category(category) => category.run(&args),
+        KaboomSubCommand::Edit(edit) => edit.run(&args),
+        KaboomSubCommand::Init(init) => init.run(&args),
+        KaboomSubCommand::List(list) => list.run(&args),
         KaboomSubCommand::Meta(meta) => meta.run(&args),
         KaboomSubCommand::Prune(prune) => prune.run(&args),
+        KaboomSubCommand::Restore(restore) => restore.run(&args),
+        KaboomSubCommand::Rm(rm) => rm.run(&args),
+        KaboomSubCommand::Show(show) => show.run(&args),
+        KaboomSubCommand::Validate(validate) => validate.run(&args),
     }
 }
@@ -0,0 +1,91 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+//! Markdown-to-HTML rendering for `kaboom add -T markdown` and friends, as a
+//! thin layer over pulldown-cmark.
+
+use pulldown_cmark::{html, Event, Parser, Tag};
+
+/// Render a whole Markdown document to HTML.
+pub fn render(src: &str) -> String {
+    let mut out = String::new();
+    html::push_html(&mut out, Parser::new(src));
+    out
+}
+
+/// Render only the first paragraph of a Markdown document to HTML (without the
+/// surrounding <p> tags), for use as a summary.
+pub fn first_paragraph(src: &str) -> Option<String> {
+    let mut events = Parser::new(src)
+        .skip_while(|event| !matches!(event, Event::Start(Tag::Paragraph)))
+        .skip(1)
+        .take_while(|event| !matches!(event, Event::End(Tag::Paragraph)))
+        .peekable();
+    events.peek()?;
+
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    Some(out)
+}
+
+#[test]
+fn render_behavior() {
+    assert_eq!(
+        "<h1>Foobar</h1>\n<p>It's like a <em>normal</em> bar, but <strong>foo</strong>.</p>\n",
+        render("# Foobar\n\nIt's like a *normal* bar, but **foo**.\n"),
+    );
+    assert_eq!(
+        "<p>see <a href=\"https://example.com/?a=1&amp;b=2\" title=\"ex\">the <code>&lt;docs&gt;</code></a></p>\n",
+        render("see [the `<docs>`](https://example.com/?a=1&b=2 \"ex\")"),
+    );
+    assert_eq!(
+        "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>\n",
+        render("```rust\nfn main() {}\n```\n"),
+    );
+    assert_eq!(
+        "<ul>\n<li>one</li>\n<li>two\n<ol>\n<li>three</li>\n</ol>\n</li>\n</ul>\n",
+        render("- one\n- two\n  1. three\n"),
+    );
+    assert_eq!(
+        "<blockquote>\n<p>quoted\ntext</p>\n</blockquote>\n<hr />\n",
+        render("> quoted\ntext\n\n---\n"),
+    );
+    assert_eq!(
+        "<p>snake_case_name &amp; <span>html</span> ©</p>\n",
+        render("snake_case_name & <span>html</span> &copy;"),
+    );
+    // Indented code and non-ASCII whitespace never start a list.
+    assert_eq!(
+        "<p>para</p>\n<pre><code>- item\n</code></pre>\n",
+        render("para\n\n    - item\n"),
+    );
+    assert_eq!("<p>\u{3000}- item</p>\n", render("\u{3000}- item\n"));
+    // Only ASCII spaces and tabs are indentation; anything else is content.
+    assert_eq!(
+        "<ul>\n<li>a\n\u{3000}\u{3000}b</li>\n</ul>\n",
+        render("- a\n\u{3000}\u{3000}b\n"),
+    );
+    assert_eq!(
+        "<ul>\n<li>a\n\u{a0}b</li>\n</ul>\n",
+        render("- a\n  \u{a0}b\n"),
+    );
+}
+
+#[test]
+fn first_paragraph_behavior() {
+    assert_eq!(
+        Some("The <em>first</em> one.".to_string()),
+        first_paragraph("# Title\n\nThe *first* one.\n\nThe second one.\n"),
+    );
+    assert_eq!(None, first_paragraph("# Just a title\n"));
+}
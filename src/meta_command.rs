@@ -11,14 +11,20 @@
 // OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
 // PERFORMANCE OF THIS SOFTWARE.
 
+use std::io::ErrorKind;
+
 use anyhow::Result;
 use argh::FromArgs;
-use atom_syndication::{Feed, Generator as AtomGenerator};
-use log::{debug, warn};
+use atom_syndication::Feed;
+use log::debug;
 
+use crate::json::OutputFormat;
 use crate::kaboom_command::KaboomCommand;
 use crate::kaboom_feed::KaboomFeed;
-use crate::stringable_link::StringableLink;
+use crate::stringable_category::StringableCategory;
+use crate::stringable_link::{merge_links, StringableLink};
+use crate::stringable_person::StringablePerson;
+use crate::text_construct::{changed_text, TextKind};
 use crate::Kaboom;
 
 #[derive(FromArgs, Debug)]
@@ -26,13 +32,17 @@ use crate::Kaboom;
 /// Arguments provided here will set or modify the metadata. After any modifications
 /// (with no flags, no modifications will be made), the new state of the feed's
 /// metadata will be dumped to standard output (by default in a human-friendly
-/// format, but JSON is planned later).
+/// format, or as JSON with the top-level *format* option).
 #[argh(subcommand, name = "meta")]
 pub struct MetaCommand {
     #[argh(option, short = 't')]
     /// a human-readable title for the feed (this must be set the first time
     /// `kaboom meta` is called on a new file)
     title: Option<String>,
+    #[argh(option)]
+    /// the type of *title*: text (the default), html, or xhtml. given without
+    /// *title*, changes the type of the existing title.
+    title_type: Option<TextKind>,
 
     #[argh(option, short = 'u')]
     /// a unique and permanent URI for this feed, often the URL at which it is
@@ -42,8 +52,8 @@ pub struct MetaCommand {
 
     #[argh(option, short = 'r')]
     /// a web page URL related to the feed, can be provided multiple times.
-    /// suffixes in the format of [rel=XXX], [type=XXX], [title=XXX], and
-    /// [lang=XXX] are all supported, for example: https://www.meteo.gc.ca/
+    /// suffixes in the format of [rel=XXX], [type=XXX], [title=XXX],
+    /// [lang=XXX], and [length=XXX] are all supported, for example: https://www.meteo.gc.ca/
     /// rss/marine/06100_f.xml[rel=alternate][lang=fr-ca][type=application/
     /// atom+xml][title=Détroit de Haro - Météo maritime - Environnement Canada]
     rel_link: Vec<StringableLink>,
@@ -53,6 +63,29 @@ pub struct MetaCommand {
     /// *existing* links, and add those links as the only links in the metadata.
     remove_links: bool,
 
+    #[argh(option, short = 'a')]
+    /// an author of the feed, in the form "Name <email> (uri)", where the email
+    /// and uri are optional. can be provided multiple times. entries added
+    /// without authors of their own inherit these.
+    author: Vec<StringablePerson>,
+    #[argh(switch, short = 'A')]
+    /// ensure that no authors are set in this feed's metadata. if *author* are
+    /// still provided, this flag will instead clear all *existing* authors, and
+    /// add those authors as the only authors in the metadata.
+    remove_authors: bool,
+
+    #[argh(option, short = 'c')]
+    /// a category (tag) of the feed as a whole, in the form
+    /// "term[scheme=URI][label=Human Label]", where both suffixes are
+    /// optional. can be provided multiple times.
+    category: Vec<StringableCategory>,
+    #[argh(switch, short = 'C')]
+    /// ensure that no categories are set in this feed's metadata. if
+    /// *category* are still provided, this flag will instead clear all
+    /// *existing* categories, and add those categories as the only categories
+    /// in the metadata.
+    remove_categories: bool,
+
     #[argh(option, short = 'i')]
     /// an optional URL pointing to a small image providing visual
     /// identification for the feed (think like a favicon)
@@ -80,127 +113,230 @@ pub struct MetaCommand {
     /// ensure that the subtitle field is not set in this feed's metadata. ignored
     /// if *subtitle* is still provided.
     remove_subtitle: bool,
+    #[argh(option)]
+    /// the type of *subtitle*: text (the default), html, or xhtml. given
+    /// without *subtitle*, changes the type of the existing subtitle.
+    subtitle_type: Option<TextKind>,
+
+    #[argh(option)]
+    /// an optional statement of the rights held in and over the feed, such as
+    /// a copyright notice
+    rights: Option<String>,
+    #[argh(switch)]
+    /// ensure that the rights field is not set in this feed's metadata. ignored
+    /// if *rights* is still provided.
+    remove_rights: bool,
+    #[argh(option)]
+    /// the type of *rights*: text (the default), html, or xhtml. given without
+    /// *rights*, changes the type of the existing rights.
+    rights_type: Option<TextKind>,
+}
 
-    #[argh(switch, short = 'G')]
-    /// do not insert the generator block into the metadata output (which
-    /// discloses within the feed that kaboom was used to generate it)
-    no_generator: bool,
+impl MetaCommand {
+    /// Whether any flag was given that could change the feed. Without one,
+    /// `meta` only shows the feed, and must neither lock nor write it.
+    fn modifies(&self) -> bool {
+        self.title.is_some()
+            || self.title_type.is_some()
+            || self.uri.is_some()
+            || !self.rel_link.is_empty()
+            || self.remove_links
+            || !self.author.is_empty()
+            || self.remove_authors
+            || !self.category.is_empty()
+            || self.remove_categories
+            || self.icon.is_some()
+            || self.remove_icon
+            || self.logo.is_some()
+            || self.remove_logo
+            || self.subtitle.is_some()
+            || self.remove_subtitle
+            || self.subtitle_type.is_some()
+            || self.rights.is_some()
+            || self.remove_rights
+            || self.rights_type.is_some()
+    }
+}
+
+fn print_meta(feed: &Feed, format: &OutputFormat) {
+    match format {
+        OutputFormat::Human => println!("{}", feed.as_human_text()),
+        OutputFormat::Json | OutputFormat::JsonLines => println!("{}", feed.as_json()),
+    }
 }
 
 impl KaboomCommand for MetaCommand {
     fn run(&self, top_args: &Kaboom) -> Result<()> {
-        let mut any_updates = false;
-        let mut feed = Feed::read_from_path(&top_args.file).unwrap_or_else(|_| {
-            let mut ret = Feed::default();
-            ret.set_updated(chrono::Utc::now());
-            ret
-        });
-
-        if let Some(title) = &self.title {
-            if title != &feed.title().to_string() {
-                feed.set_title(title.clone());
-                any_updates = true;
+        if !self.modifies() {
+            print_meta(&Feed::read_from_path(&top_args.file)?, &top_args.format);
+            return Ok(());
+        }
+
+        let _lock = Feed::lock(&top_args.file, top_args.wait, top_args.no_op)?;
+
+        // Only start from scratch if there's genuinely nothing there yet: a
+        // feed that exists but can't be read must never be silently replaced.
+        let mut feed = match std::fs::metadata(&top_args.file) {
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                debug!(
+                    "{} does not exist, creating a new feed",
+                    top_args.file.to_string_lossy()
+                );
+                Feed::default()
             }
+            _ => Feed::read_from_path(&top_args.file)?,
+        };
+        let original = feed.clone();
+
+        if let Some(title) = changed_text(
+            "title",
+            Some(feed.title()),
+            self.title.as_deref(),
+            self.title_type,
+        )? {
+            feed.set_title(title);
         }
 
         if let Some(uri) = &self.uri {
             if uri != &feed.id().to_string() {
                 feed.set_id(uri.clone());
-                any_updates = true;
             }
         }
 
         if self.remove_subtitle && self.subtitle.is_none() {
             feed.set_subtitle(None);
-            any_updates = true;
         }
 
-        if let Some(subtitle) = &self.subtitle {
-            let text_contents = atom_syndication::Text::from(subtitle.as_str());
-            let update_needed = match feed.subtitle() {
-                Some(existing_st) => &text_contents != existing_st,
-                None => true,
-            };
+        if let Some(subtitle) = changed_text(
+            "subtitle",
+            feed.subtitle(),
+            self.subtitle.as_deref(),
+            self.subtitle_type,
+        )? {
+            feed.set_subtitle(Some(subtitle));
+        }
 
-            if update_needed {
-                feed.set_subtitle(Some(text_contents));
-            }
+        if self.remove_rights && self.rights.is_none() {
+            feed.set_rights(None);
+        }
 
-            any_updates = true;
+        if let Some(rights) = changed_text(
+            "rights",
+            feed.rights(),
+            self.rights.as_deref(),
+            self.rights_type,
+        )? {
+            feed.set_rights(Some(rights));
         }
 
         if self.remove_icon && self.icon.is_none() {
             feed.set_icon(None);
-            any_updates = true;
         }
 
         if self.icon.is_some() && self.icon != feed.icon().map(|it| it.to_string()) {
             feed.set_icon(self.icon.clone());
-            any_updates = true;
         }
 
         if self.remove_logo && self.logo.is_none() {
             feed.set_logo(None);
-            any_updates = true;
         }
 
         if self.logo.is_some() && self.logo != feed.logo().map(|it| it.to_string()) {
             feed.set_logo(self.logo.clone());
-            any_updates = true;
         }
 
-        if self.remove_links {
-            feed.set_links(Vec::with_capacity(self.rel_link.len()));
-            any_updates = true;
+        if self.remove_authors {
+            feed.set_authors(Vec::with_capacity(self.author.len()));
         }
 
-        for rel_link in &self.rel_link {
-            let rl = &rel_link.link_form;
-            if let Some(existing) = feed.links.iter_mut().find(|link| link.href() == rl.href()) {
-                let same = existing == rl;
-                debug!(
-                    "link {} aleady exists, {}",
-                    &rel_link.string_form,
-                    if same {
-                        "seems to be equivalent, skipping!"
-                    } else {
-                        "modifying in place"
-                    }
-                );
-
-                if !same {
-                    existing.set_rel(rl.rel());
-                    existing.set_hreflang(rl.hreflang.clone());
-                    existing.set_mime_type(rl.mime_type.clone());
-                    existing.set_title(rl.title.clone());
-                    any_updates = true;
+        for author in &self.author {
+            let person = &author.person_form;
+            if let Some(existing) = feed
+                .authors
+                .iter_mut()
+                .find(|it| it.name() == person.name())
+            {
+                if existing != person {
+                    debug!(
+                        "author {} already exists, modifying in place",
+                        person.name()
+                    );
+                    *existing = person.clone();
                 }
             } else {
-                feed.links.push(rl.clone());
-                any_updates = true;
+                feed.authors.push(person.clone());
             }
         }
 
-        if any_updates {
-            feed.set_updated(chrono::Utc::now());
+        if self.remove_categories {
+            feed.set_categories(Vec::with_capacity(self.category.len()));
+        }
 
-            if !self.no_generator {
-                feed.set_generator(AtomGenerator {
-                    value: crate::APP_NAME.to_string(),
-                    uri: Some(crate::APP_HOMEPAGE.into()),
-                    version: Some(crate::VERSION.to_string()),
-                });
+        for category in &self.category {
+            let cat = &category.category_form;
+            if let Some(existing) = feed
+                .categories
+                .iter_mut()
+                .find(|it| it.term() == cat.term() && it.scheme() == cat.scheme())
+            {
+                if existing != cat {
+                    debug!("category {} already exists, modifying in place", cat.term());
+                    *existing = cat.clone();
+                }
+            } else {
+                feed.categories.push(cat.clone());
             }
         }
 
-        if top_args.no_op {
-            warn!("not writing results to disk because no-op was requested");
-        } else {
-            feed.write_to_path(&top_args.file)?;
+        if self.remove_links {
+            feed.set_links(Vec::with_capacity(self.rel_link.len()));
         }
 
-        println!("{}", feed.as_human_text());
+        merge_links(&mut feed.links, &self.rel_link);
+
+        feed.stamp_if_modified(&original, top_args.feed_updated, !top_args.no_generator);
+        feed.write_or_preview(&original, &top_args.file, top_args.no_op)?;
+
+        print_meta(&feed, &top_args.format);
 
         Ok(())
     }
 }
+
+#[test]
+fn meta_without_flags_behavior() {
+    let dir = std::env::temp_dir().join(format!("kaboom-meta-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("feed.xml");
+    let path_arg = path.to_string_lossy().to_string();
+    let run = |args: &[&str]| {
+        let args = Kaboom::from_args(&["kaboom"], args).unwrap();
+        match &args.command {
+            crate::KaboomSubCommand::Meta(meta) => meta.run(&args),
+            _ => unreachable!(),
+        }
+    };
+
+    // Showing a feed that doesn't exist yet must not create it, or its lock.
+    assert!(run(&["-f", &path_arg, "meta"]).is_err());
+    assert!(!path.exists());
+    assert!(!dir.join("feed.xml.lock").exists());
+
+    // Nor may a dry run of a change.
+    run(&["-f", &path_arg, "-n", "meta", "-t", "T", "-u", "urn:x"]).unwrap();
+    assert!(!path.exists());
+    assert!(!dir.join("feed.xml.lock").exists());
+
+    run(&["-f", &path_arg, "meta", "-t", "T", "-u", "urn:x"]).unwrap();
+    std::fs::remove_file(dir.join("feed.xml.lock")).unwrap();
+    let written = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+    run(&["-f", &path_arg, "meta"]).unwrap();
+    assert_eq!(
+        written,
+        std::fs::metadata(&path).unwrap().modified().unwrap()
+    );
+    assert!(!dir.join("feed.xml.lock").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -14,14 +14,16 @@
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use argh::FromArgs;
 use atom_syndication::{Entry as AtomEntry, Feed};
 use chrono::{DateTime, Utc};
-use log::warn;
+use log::{debug, warn};
 
+use crate::archive::{archive_entries, link_archives_to_come, ArchivePeriod};
 use crate::kaboom_command::KaboomCommand;
-use crate::kaboom_feed::KaboomFeed;
+use crate::kaboom_entry::{warn_missing_dates, DateOrder, KaboomEntry};
+use crate::kaboom_feed::{default_reject_path, KaboomFeed};
 use crate::Kaboom;
 
 type AtomEntries = Vec<AtomEntry>;
@@ -31,6 +33,9 @@ pub enum PruneStrategy {
     RecentlyPublished,
     RecentlyUpdated,
     SinceDate,
+    MaxAge,
+    MaxBytes,
+    Combined,
 }
 
 impl Default for PruneStrategy {
@@ -47,6 +52,9 @@ impl FromStr for PruneStrategy {
             "published" => Ok(Self::RecentlyPublished),
             "updated" => Ok(Self::RecentlyUpdated),
             "since-date" => Ok(Self::SinceDate),
+            "max-age" => Ok(Self::MaxAge),
+            "max-bytes" => Ok(Self::MaxBytes),
+            "combined" => Ok(Self::Combined),
             _ => Err("unknown pruning strategy"),
         }
     }
@@ -59,8 +67,9 @@ impl FromStr for PruneStrategy {
 pub struct PruneCommand {
     #[argh(positional)]
     /// number of entries to keep in the feed, as sorted by *strategy*,
-    /// described below
-    count: usize,
+    /// described below. required by the published and updated strategies; for
+    /// the others, an optional upper bound.
+    count: Option<usize>,
 
     #[argh(option, short = 'R', default = "false")]
     /// skip sending pruned entries to the *reject_file*, described below
@@ -69,51 +78,174 @@ pub struct PruneCommand {
     #[argh(option, short = 'r')]
     /// path to an Atom file (which will be created if it does not yet exist,
     /// sharing all metadata from the original feed) to store pruned entries for
-    /// backup/archival purposes.
+    /// backup/archival purposes. entries already in this file are kept, and
+    /// newly pruned entries are merged in.
     ///
     /// by default, this will be <feed file> with any .xml extension removed, and
     /// then ".rej.xml" added
     reject_file: Option<PathBuf>,
 
+    #[argh(option, short = 'a')]
+    /// instead of the *reject_file*, roll pruned entries into RFC 5005 archive
+    /// documents next to the feed, one per year or month (for feed.xml, named
+    /// like feed-2023.xml or feed-2023-03.xml), linked to each other and to the
+    /// feed so that readers can page through the full history. requires the
+    /// feed to have a rel=self link.
+    archive: Option<ArchivePeriod>,
+
     #[argh(option, short = 's', default = "PruneStrategy::default()")]
     /// strategy used in pruning entries from the feed: published, for date
-    /// of publication, updated, for date of most recent update, or since-
-    /// date, which preserves only those articles authored since *since-date*,
-    /// described below
+    /// of publication (or whichever date the top-level *date_order* picks),
+    /// updated, for date of most recent update, since-date,
+    /// which preserves only those articles authored since *since*, max-age,
+    /// which preserves only those articles authored within *max_age*,
+    /// max-bytes, which preserves as many of the most recently published
+    /// articles as fit within *max_bytes*, or combined, which applies every
+    /// limit among *count*, *since*, *max_age*, and *max_bytes* that is given
     strategy: PruneStrategy,
 
-    #[argh(option, short = 'd', default = "chrono::Utc::now()")]
-    /// a date and time, in RFC3339 format, used only with the since-date
-    /// *strategy*, described above
-    since: DateTime<Utc>,
+    #[argh(option, short = 'd')]
+    /// a date and time, in RFC3339 format, used with the since-date (where it
+    /// defaults to now) and combined *strategy*, described above
+    since: Option<DateTime<Utc>>,
+
+    #[argh(option, short = 'm')]
+    /// a duration such as 90d, 6months, or 1y 2w, used with the max-age and
+    /// combined *strategy*, described above
+    max_age: Option<humantime::Duration>,
+
+    #[argh(option, short = 'b')]
+    /// a size in bytes that the written feed must not exceed, used with the
+    /// max-bytes and combined *strategy*, described above. pinned entries and
+    /// *min_per_category*/*min_per_author* win over the budget: they are kept
+    /// even if that leaves the feed over it, with a warning.
+    max_bytes: Option<usize>,
+
+    #[argh(option)]
+    /// keep at least this many of the most recent entries in a category, in
+    /// the form term=N, even if *strategy* would prune them. can be provided
+    /// multiple times.
+    min_per_category: Vec<Quota>,
+
+    #[argh(option)]
+    /// keep at most this many of the most recent entries in a category, in
+    /// the form term=N. can be provided multiple times.
+    max_per_category: Vec<Quota>,
+
+    #[argh(option)]
+    /// keep at least this many of the most recent entries by an author, in
+    /// the form name=N, even if *strategy* would prune them. can be provided
+    /// multiple times.
+    min_per_author: Vec<Quota>,
+
+    #[argh(option)]
+    /// keep at most this many of the most recent entries by an author, in the
+    /// form name=N. can be provided multiple times.
+    max_per_author: Vec<Quota>,
+
+    #[argh(option, default = "String::from(\"pinned\")")]
+    /// entries in this category are never pruned, regardless of *strategy* or
+    /// any of the maximums above
+    pin_category: String,
+}
+
+/// A limit on how many entries sharing some *key* (a category term or an
+/// author's name) may be kept, given on the command line as key=N.
+#[derive(Debug, PartialEq)]
+pub struct Quota {
+    key: String,
+    limit: usize,
+}
+
+impl FromStr for Quota {
+    type Err = &'static str;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        let (key, limit) = it
+            .rsplit_once('=')
+            .ok_or("quotas must be in the form key=N")?;
+
+        if key.is_empty() {
+            return Err("quotas must be in the form key=N");
+        }
+
+        Ok(Self {
+            key: key.to_string(),
+            limit: limit
+                .parse()
+                .map_err(|_| "quota limits must be non-negative whole numbers")?,
+        })
+    }
 }
 
 impl KaboomCommand for PruneCommand {
     fn run(&self, top_args: &Kaboom) -> Result<()> {
+        let _lock = Feed::lock(&top_args.file, top_args.wait, top_args.no_op)?;
         let mut feed = Feed::read_from_path(&top_args.file)?;
+        let original = feed.clone();
+
+        // Everything that happens to the feed after pruning, so that
+        // *max_bytes* is measured against the feed exactly as it'll be written.
+        let now = Utc::now().into();
+        let finish = |feed: &mut Feed, rejected: &[AtomEntry]| -> Result<()> {
+            feed.stamp_if_modified_at(
+                &original,
+                top_args.feed_updated,
+                !top_args.no_generator,
+                now,
+            );
+            if let Some(period) = self.archive {
+                link_archives_to_come(feed, rejected, &top_args.file, period, top_args.date_order)?;
+            }
+            Ok(())
+        };
 
-        if feed.entries().len() <= self.count {
-            warn!("not pruning anything because feed already includes <= target count");
+        let rejected = self.truncate_returning_rejects(&mut feed, top_args.date_order, finish)?;
+
+        if rejected.is_empty() {
+            warn!("not pruning anything because feed already fits within the requested limits");
         } else {
-            let rejected = self.truncate_returning_rejects(&mut feed.entries);
+            feed.stamp_if_modified_at(
+                &original,
+                top_args.feed_updated,
+                !top_args.no_generator,
+                now,
+            );
 
-            if self.no_reject {
+            if let Some(period) = self.archive {
+                archive_entries(
+                    &mut feed,
+                    rejected,
+                    &top_args.file,
+                    period,
+                    top_args.date_order,
+                    top_args.no_op,
+                )?;
+            } else if self.no_reject {
                 warn!("not writing pruned entries anywhere for backup because no-reject was requested");
             } else {
-                let mut rej_feed = feed.clone();
-                rej_feed.set_entries(rejected);
-                rej_feed.write_to_path(&self.reject_file.clone().unwrap_or_else(|| {
-                    let mut rej_path = top_args.file.clone();
+                feed.write_rejects_to_path(
+                    rejected,
+                    &self
+                        .reject_file
+                        .clone()
+                        .unwrap_or_else(|| default_reject_path(&top_args.file)),
+                    top_args.date_order,
+                    top_args.no_op,
+                )?;
+            }
 
-                    if let Some("xml") = rej_path.extension().and_then(|e| e.to_str()) {
-                        rej_path.set_extension("rej.xml");
-                    }
+            feed.write_or_preview(&original, &top_args.file, top_args.no_op)?;
+        }
 
-                    rej_path
-                }))?;
+        if let Some(max_bytes) = self.byte_budget() {
+            let size = feed.write_to(Vec::new())?.len();
+            if size > max_bytes {
+                warn!(
+                    "the feed is {} bytes, over the --max-bytes budget of {}, because pinned entries, --min-per-* quotas, and the feed's own metadata are kept regardless",
+                    size, max_bytes
+                );
             }
-
-            feed.write_to_path(&top_args.file)?;
         }
 
         Ok(())
@@ -121,32 +253,403 @@ impl KaboomCommand for PruneCommand {
 }
 
 impl PruneCommand {
-    /// Sort the entries based on the desired strategy, and retain only as many
-    /// in *entries* as necessary to fulfil criteria (modifying the input Vec
-    /// in-place). Return the remainder as a new Vec.
-    fn truncate_returning_rejects(&self, entries: &mut AtomEntries) -> AtomEntries {
-        match self.strategy {
-            PruneStrategy::RecentlyPublished => {
-                entries.sort_by_key(|it| it.published);
-                entries.reverse();
-                entries.split_off(self.count)
-            }
-            PruneStrategy::RecentlyUpdated => {
-                entries.sort_by_key(|it| it.updated);
-                entries.reverse();
-                entries.split_off(self.count)
+    /// Sort the entries of *feed* based on the desired strategy (dating them
+    /// by *order*, unless the strategy is updated), and retain only as many as
+    /// necessary to fulfil criteria (modifying the feed in-place). Return the
+    /// remainder as a new Vec. *finish* is applied to trial copies of the
+    /// pruned feed before measuring them against *max_bytes*, and should do
+    /// to them whatever will be done to the real one before it's written.
+    fn truncate_returning_rejects(
+        &self,
+        feed: &mut Feed,
+        order: DateOrder,
+        finish: impl Fn(&mut Feed, &[AtomEntry]) -> Result<()>,
+    ) -> Result<AtomEntries> {
+        let require_count = || {
+            self.count
+                .ok_or_else(|| anyhow!("a count is required by this pruning strategy"))
+        };
+        let require_max_age = || {
+            self.max_age
+                .ok_or_else(|| anyhow!("--max-age is required by the max-age pruning strategy"))
+        };
+        let require_max_bytes = || {
+            self.max_bytes
+                .ok_or_else(|| anyhow!("--max-bytes is required by the max-bytes pruning strategy"))
+        };
+
+        let order = if self.strategy == PruneStrategy::RecentlyUpdated {
+            DateOrder::UpdatedOnly
+        } else {
+            order
+        };
+        warn_missing_dates(&feed.entries, order);
+        feed.entries.sort_by_key(|it| *it.date(order));
+        feed.entries.reverse();
+
+        let mut keep = feed.entries.len();
+        let (count, since, max_age, max_bytes) = match self.strategy {
+            PruneStrategy::RecentlyPublished | PruneStrategy::RecentlyUpdated => {
+                (Some(require_count()?), None, None, None)
             }
-            PruneStrategy::SinceDate => {
-                entries.sort_by_key(|it| it.published);
-                entries.reverse();
-                let ppoint = entries
-                    .partition_point(|e| e.published().map_or(false, |pubd| pubd >= &self.since));
-                if ppoint > self.count {
-                    entries.split_off(self.count)
-                } else {
-                    entries.split_off(ppoint)
+            PruneStrategy::SinceDate => (
+                self.count,
+                Some(self.since.unwrap_or_else(Utc::now)),
+                None,
+                None,
+            ),
+            PruneStrategy::MaxAge => (self.count, None, Some(require_max_age()?), None),
+            PruneStrategy::MaxBytes => (self.count, None, None, Some(require_max_bytes()?)),
+            PruneStrategy::Combined => {
+                if self.count.is_none()
+                    && self.since.is_none()
+                    && self.max_age.is_none()
+                    && self.max_bytes.is_none()
+                {
+                    bail!("the combined pruning strategy needs at least one of a count, --since, --max-age, or --max-bytes");
                 }
+
+                (self.count, self.since, self.max_age, self.max_bytes)
             }
+        };
+
+        if let Some(count) = count {
+            keep = keep.min(count);
+        }
+
+        if let Some(since) = since {
+            keep = keep.min(dated_since(&feed.entries, since, order));
+        }
+
+        if let Some(max_age) = max_age {
+            let max_age = chrono::Duration::from_std(max_age.into())
+                .map_err(|_| anyhow!("--max-age {} is too long", max_age))?;
+            keep = keep.min(dated_since(&feed.entries, Utc::now() - max_age, order));
         }
+
+        // Pins and quotas are part of every trial, since they're kept (or
+        // cut) no matter what, and so take their share of the budget first.
+        if let Some(max_bytes) = max_bytes {
+            let size_with = |count: usize| -> Result<usize> {
+                let (kept, cut) =
+                    partition_kept(feed.entries.clone(), &self.keep_mask(feed, count));
+                let mut trial = feed.clone();
+                trial.entries = kept;
+                finish(&mut trial, &cut)?;
+                Ok(trial.write_to(Vec::new())?.len())
+            };
+            keep = fitting_within_bytes(keep, max_bytes, size_with)?;
+        }
+
+        let keep = self.keep_mask(feed, keep);
+        let (kept, rejected) = partition_kept(std::mem::take(&mut feed.entries), &keep);
+        feed.entries = kept;
+
+        Ok(rejected)
+    }
+
+    /// Which of the entries of *feed* (sorted newest-first) to keep, if the
+    /// *strategy* keeps the first *count* of them, once quotas are applied.
+    fn keep_mask(&self, feed: &Feed, count: usize) -> Vec<bool> {
+        let mut keep: Vec<bool> = (0..feed.entries.len()).map(|idx| idx < count).collect();
+        self.apply_quotas(&feed.entries, &mut keep);
+        keep
     }
+
+    /// The byte budget in effect for this *strategy*, if any.
+    fn byte_budget(&self) -> Option<usize> {
+        match self.strategy {
+            PruneStrategy::MaxBytes | PruneStrategy::Combined => self.max_bytes,
+            _ => None,
+        }
+    }
+
+    /// Adjust *keep* (whether each of *entries*, sorted newest-first, survives
+    /// the *strategy*) to honour the per-category and per-author quotas: first
+    /// minimums pull the most recent matching entries back in, then maximums
+    /// push the oldest matching entries out. Pinned entries are always kept.
+    fn apply_quotas(&self, entries: &[AtomEntry], keep: &mut [bool]) {
+        let in_category =
+            |entry: &AtomEntry, term: &str| entry.categories().iter().any(|it| it.term() == term);
+        let by_author =
+            |entry: &AtomEntry, name: &str| entry.authors().iter().any(|it| it.name() == name);
+
+        for quota in &self.min_per_category {
+            raise_to_minimum(entries, keep, quota.limit, |it| in_category(it, &quota.key));
+        }
+
+        for quota in &self.min_per_author {
+            raise_to_minimum(entries, keep, quota.limit, |it| by_author(it, &quota.key));
+        }
+
+        for quota in &self.max_per_category {
+            cap_at_maximum(entries, keep, quota.limit, |it| {
+                in_category(it, &quota.key) && !in_category(it, &self.pin_category)
+            });
+        }
+
+        for quota in &self.max_per_author {
+            cap_at_maximum(entries, keep, quota.limit, |it| {
+                by_author(it, &quota.key) && !in_category(it, &self.pin_category)
+            });
+        }
+
+        for (entry, keep) in entries.iter().zip(keep.iter_mut()) {
+            if in_category(entry, &self.pin_category) {
+                *keep = true;
+            }
+        }
+    }
+}
+
+/// Mark the first *limit* of *entries* that *matches* as kept.
+fn raise_to_minimum(
+    entries: &[AtomEntry],
+    keep: &mut [bool],
+    limit: usize,
+    matches: impl Fn(&AtomEntry) -> bool,
+) {
+    entries
+        .iter()
+        .zip(keep.iter_mut())
+        .filter(|(entry, _)| matches(entry))
+        .take(limit)
+        .for_each(|(_, keep)| *keep = true);
+}
+
+/// Mark all but the first *limit* of the kept *entries* that *matches* as not
+/// kept.
+fn cap_at_maximum(
+    entries: &[AtomEntry],
+    keep: &mut [bool],
+    limit: usize,
+    matches: impl Fn(&AtomEntry) -> bool,
+) {
+    entries
+        .iter()
+        .zip(keep.iter_mut())
+        .filter(|(entry, keep)| **keep && matches(entry))
+        .skip(limit)
+        .for_each(|(entry, keep)| {
+            debug!("{} is over its quota, pruning", entry.id());
+            *keep = false;
+        });
+}
+
+#[test]
+fn apply_quotas_behavior() {
+    use atom_syndication::{Category, Person};
+
+    let entry = |id: &str, category: &str, author: &str| AtomEntry {
+        id: id.into(),
+        categories: vec![Category {
+            term: category.into(),
+            ..Default::default()
+        }],
+        authors: vec![Person {
+            name: author.into(),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let entries = vec![
+        entry("note-3", "notes", "alice"),
+        entry("note-2", "notes", "alice"),
+        entry("note-1", "notes", "bob"),
+        entry("essay-2", "essays", "alice"),
+        entry("essay-1", "essays", "bob"),
+        entry("welcome", "pinned", "bob"),
+    ];
+    let kept = |args: &[&str]| {
+        let cmd = PruneCommand::from_args(&["prune"], args).unwrap();
+        let mut keep = [true, true, true, false, false, false];
+        cmd.apply_quotas(&entries, &mut keep);
+        entries
+            .iter()
+            .zip(keep)
+            .filter(|(_, keep)| *keep)
+            .map(|(it, _)| it.id())
+            .collect::<Vec<&str>>()
+    };
+
+    assert_eq!(vec!["note-3", "note-2", "note-1", "welcome"], kept(&[]));
+    assert_eq!(
+        vec!["note-3", "note-2", "note-1", "essay-2", "welcome"],
+        kept(&["--min-per-category", "essays=1"])
+    );
+    assert_eq!(
+        vec!["note-3", "essay-2", "essay-1", "welcome"],
+        kept(&[
+            "--min-per-category",
+            "essays=5",
+            "--max-per-category",
+            "notes=1"
+        ])
+    );
+    assert_eq!(
+        vec!["note-3", "note-1", "essay-1"],
+        kept(&[
+            "--min-per-author",
+            "bob=2",
+            "--max-per-author",
+            "alice=1",
+            "--pin-category",
+            "nothing"
+        ])
+    );
+}
+
+/// Split *entries* into those that *keep* says to keep, and the rest.
+fn partition_kept(entries: AtomEntries, keep: &[bool]) -> (AtomEntries, AtomEntries) {
+    let (mut kept, mut rejected) = (AtomEntries::new(), AtomEntries::new());
+    for (entry, keep) in entries.into_iter().zip(keep) {
+        if *keep {
+            kept.push(entry);
+        } else {
+            rejected.push(entry);
+        }
+    }
+
+    (kept, rejected)
+}
+
+/// How many of *entries* (sorted newest-first by *order*) are dated at or after
+/// *since*.
+fn dated_since(entries: &[AtomEntry], since: DateTime<Utc>, order: DateOrder) -> usize {
+    entries.partition_point(|e| e.date(order) >= &since)
+}
+
+/// The largest number of leading entries, up to *at_most*, that can be kept
+/// while the serialized feed stays within *max_bytes*, where *size_with* gives
+/// the serialized size of the feed when keeping a given number of entries.
+fn fitting_within_bytes(
+    at_most: usize,
+    max_bytes: usize,
+    size_with: impl Fn(usize) -> Result<usize>,
+) -> Result<usize> {
+    if size_with(0)? > max_bytes {
+        debug!(
+            "the feed is larger than {} bytes with as few entries as possible",
+            max_bytes
+        );
+        return Ok(0);
+    }
+
+    // Serialized size only grows as entries are added, so binary search for
+    // the last count that fits.
+    let (mut fits, mut too_big) = (0, at_most + 1);
+    while too_big - fits > 1 {
+        let mid = fits + (too_big - fits) / 2;
+        if size_with(mid)? <= max_bytes {
+            fits = mid;
+        } else {
+            too_big = mid;
+        }
+    }
+
+    debug!("{} entries fit within {} bytes", fits, max_bytes);
+    Ok(fits)
+}
+
+#[test]
+fn fitting_within_bytes_behavior() {
+    let mut feed = Feed {
+        title: "T".into(),
+        id: "https://example.com/feed.xml".into(),
+        ..Default::default()
+    };
+    let empty_size = feed.write_to(Vec::new()).unwrap().len();
+
+    feed.entries = (0..10)
+        .map(|idx| AtomEntry {
+            id: format!("https://example.com/{}", idx),
+            title: "x".repeat(100).into(),
+            ..Default::default()
+        })
+        .collect();
+    let full_size = feed.write_to(Vec::new()).unwrap().len();
+    let entry_size = (full_size - empty_size) / 10;
+    let size_with = |count: usize| -> Result<usize> {
+        let mut trial = feed.clone();
+        trial.entries.truncate(count);
+        Ok(trial.write_to(Vec::new())?.len())
+    };
+
+    assert_eq!(10, fitting_within_bytes(10, full_size, size_with).unwrap());
+    assert_eq!(4, fitting_within_bytes(4, full_size, size_with).unwrap());
+    assert_eq!(
+        9,
+        fitting_within_bytes(10, full_size - 1, size_with).unwrap()
+    );
+    assert_eq!(
+        3,
+        fitting_within_bytes(10, empty_size + entry_size * 3 + 1, size_with).unwrap()
+    );
+    assert_eq!(0, fitting_within_bytes(10, empty_size, size_with).unwrap());
+    assert_eq!(0, fitting_within_bytes(10, 1, size_with).unwrap());
+}
+
+#[test]
+fn max_bytes_covers_written_feed_behavior() {
+    use atom_syndication::FixedDateTime;
+
+    let dir = std::env::temp_dir().join(format!("kaboom-prune-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("feed.xml");
+
+    let updated: FixedDateTime = "2023-07-16T01:23:52Z".parse().unwrap();
+    let mut feed = Feed {
+        title: "T".into(),
+        id: "https://example.com/feed.xml".into(),
+        updated,
+        ..Default::default()
+    };
+    feed.entries = (0..20)
+        .map(|idx| AtomEntry {
+            id: format!("https://example.com/{}", idx),
+            title: "x".repeat(100).into(),
+            updated: updated - chrono::Duration::days(idx * 40),
+            ..Default::default()
+        })
+        .collect();
+    feed.write_to_path(&path).unwrap();
+
+    // Five entries would fit exactly as the feed stands, but bumping the
+    // feed's updated date, adding the generator, and linking the newest
+    // archive all grow it after pruning, and must be accounted for too.
+    let mut five = feed.clone();
+    five.entries.truncate(5);
+    let max_bytes = five.write_to(Vec::new()).unwrap().len();
+    let path_arg = path.to_string_lossy().to_string();
+    let budget_arg = max_bytes.to_string();
+    let args = crate::Kaboom::from_args(
+        &["kaboom"],
+        &[
+            "-f",
+            &path_arg,
+            "--feed-updated",
+            "now",
+            "prune",
+            "-s",
+            "max-bytes",
+            "-b",
+            &budget_arg,
+            "-a",
+            "year",
+        ],
+    )
+    .unwrap();
+    match &args.command {
+        crate::KaboomSubCommand::Prune(prune) => prune.run(&args).unwrap(),
+        _ => unreachable!(),
+    }
+
+    let written = std::fs::read(&path).unwrap();
+    let pruned = Feed::read_from(&written[..]).unwrap();
+    assert!(written.len() <= max_bytes);
+    assert!(!pruned.entries().is_empty() && pruned.entries().len() < 20);
+    assert!(pruned.generator().is_some());
+    assert!(pruned.links().iter().any(|it| it.rel() == "prev-archive"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
 }
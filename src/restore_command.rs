@@ -0,0 +1,223 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use argh::FromArgs;
+use atom_syndication::{Entry as AtomEntry, Feed};
+use chrono::{DateTime, FixedOffset, Utc};
+use log::{debug, warn};
+
+use crate::kaboom_command::KaboomCommand;
+use crate::kaboom_entry::{warn_missing_dates, DateOrder, KaboomEntry};
+use crate::kaboom_feed::{default_reject_path, rejected_at, unmark_rejected, KaboomFeed};
+use crate::rm_command::glob_matches;
+use crate::Kaboom;
+
+#[derive(FromArgs, Debug)]
+/// Move entries from the reject file back into the Atom feed, undoing `kaboom
+/// prune` or `kaboom rm`. Restored entries are slotted into the feed by date,
+/// and removed from the reject file. The feed is written before the reject
+/// file, so if anything goes wrong part way, an entry may end up in both files,
+/// but never in neither.
+#[argh(subcommand, name = "restore")]
+pub struct RestoreCommand {
+    #[argh(positional)]
+    /// the exact URI(s) of the entries to restore
+    ids: Vec<String>,
+
+    #[argh(option, short = 'g')]
+    /// restore all rejected entries whose URI matches this glob, in the same
+    /// syntax as `kaboom rm`. can be provided multiple times.
+    glob: Vec<String>,
+
+    #[argh(switch, short = 'l')]
+    /// restore everything that was rejected by the most recent `kaboom prune`
+    /// or `kaboom rm`
+    last: bool,
+
+    #[argh(option, short = 'd')]
+    /// only restore entries dated at or after this date and time, in RFC3339
+    /// format. entries are dated as picked by the top-level *date_order*. if
+    /// no ids, globs, or *last* are given, restores every entry in this range.
+    since: Option<DateTime<Utc>>,
+
+    #[argh(option, short = 'D')]
+    /// only restore entries dated at or before this date and time, in RFC3339
+    /// format. see *since* for which date is used.
+    until: Option<DateTime<Utc>>,
+
+    #[argh(option, short = 'r')]
+    /// path to the Atom file to restore entries from. defaults to the same
+    /// reject file as `kaboom prune`.
+    reject_file: Option<PathBuf>,
+}
+
+impl KaboomCommand for RestoreCommand {
+    fn run(&self, top_args: &Kaboom) -> Result<()> {
+        if self.ids.is_empty()
+            && self.glob.is_empty()
+            && !self.last
+            && self.since.is_none()
+            && self.until.is_none()
+        {
+            bail!("at least one id, glob, date, or --last must be provided to select entries to restore");
+        }
+
+        let rej_path = self
+            .reject_file
+            .clone()
+            .unwrap_or_else(|| default_reject_path(&top_args.file));
+        let _lock = Feed::lock(&top_args.file, top_args.wait, top_args.no_op)?;
+        let mut feed = Feed::read_from_path(&top_args.file)?;
+        let original = feed.clone();
+        let mut rej_feed = Feed::read_from_path(&rej_path).with_context(|| {
+            format!("could not read reject file {}", rej_path.to_string_lossy())
+        })?;
+        let rej_original = rej_feed.clone();
+
+        for id in &self.ids {
+            if !rej_feed.entries().iter().any(|entry| entry.id() == id) {
+                warn!("no entry with id {} exists in the reject file", id);
+            }
+        }
+
+        let last_rejected = rej_feed.entries().iter().filter_map(rejected_at).max();
+        if self.last && last_rejected.is_none() {
+            warn!("no entries in the reject file record when they were rejected, so --last matches nothing");
+        }
+
+        let (selected, mut kept): (Vec<AtomEntry>, Vec<AtomEntry>) = rej_feed
+            .entries
+            .drain(..)
+            .partition(|entry| self.matches(entry, last_rejected, top_args.date_order));
+        warn_missing_dates(&selected, top_args.date_order);
+
+        let mut restored = 0;
+        for mut entry in selected {
+            if feed.entries().iter().any(|it| it.id() == entry.id()) {
+                warn!(
+                    "not restoring {} because an entry with that id is already in the feed",
+                    entry.id()
+                );
+                kept.push(entry);
+                continue;
+            }
+
+            debug!("restoring {}", entry.id());
+            unmark_rejected(&mut entry);
+            insert_by_date(&mut feed.entries, entry, top_args.date_order);
+            restored += 1;
+        }
+
+        if restored == 0 {
+            warn!("not restoring anything because no entries matched");
+            return Ok(());
+        }
+
+        rej_feed.set_entries(
+            rej_original
+                .entries()
+                .iter()
+                .filter(|it| kept.iter().any(|k| k.id() == it.id()))
+                .cloned()
+                .collect::<Vec<AtomEntry>>(),
+        );
+
+        feed.stamp_if_modified(&original, top_args.feed_updated, !top_args.no_generator);
+        feed.write_or_preview(&original, &top_args.file, top_args.no_op)?;
+        rej_feed.write_or_preview(&rej_original, &rej_path, top_args.no_op)?;
+
+        Ok(())
+    }
+}
+
+impl RestoreCommand {
+    fn matches(
+        &self,
+        entry: &AtomEntry,
+        last_rejected: Option<DateTime<FixedOffset>>,
+        order: DateOrder,
+    ) -> bool {
+        let selected = (self.ids.is_empty() && self.glob.is_empty() && !self.last)
+            || self.ids.iter().any(|id| entry.id() == id)
+            || self.glob.iter().any(|glob| glob_matches(glob, entry.id()))
+            || (self.last && last_rejected.is_some() && rejected_at(entry) == last_rejected);
+
+        let date = entry.date(order);
+        selected
+            && self.since.map_or(true, |since| date >= &since)
+            && self.until.map_or(true, |until| date <= &until)
+    }
+}
+
+/// Insert *entry* into *entries* (assumed to be sorted newest-first by
+/// *order*) just before the first entry older than it.
+pub fn insert_by_date(entries: &mut Vec<AtomEntry>, entry: AtomEntry, order: DateOrder) {
+    let idx = entries
+        .iter()
+        .position(|it| it.date(order) < entry.date(order))
+        .unwrap_or(entries.len());
+    entries.insert(idx, entry);
+}
+
+#[test]
+fn insert_by_date_behavior() {
+    let entry = |id: &str, published: Option<&str>, updated: &str| AtomEntry {
+        id: id.into(),
+        published: published.map(|p| p.parse().unwrap()),
+        updated: updated.parse().unwrap(),
+        ..Default::default()
+    };
+
+    let mut entries = vec![
+        entry("c", Some("2023-03-01T00:00:00Z"), "2023-03-01T00:00:00Z"),
+        entry("a", Some("2023-01-01T00:00:00Z"), "2023-04-01T00:00:00Z"),
+    ];
+
+    insert_by_date(
+        &mut entries,
+        entry("b", None, "2023-02-01T00:00:00Z"),
+        DateOrder::PublishedThenUpdated,
+    );
+    insert_by_date(
+        &mut entries,
+        entry("d", Some("2023-05-01T00:00:00Z"), "2023-05-01T00:00:00Z"),
+        DateOrder::PublishedThenUpdated,
+    );
+    insert_by_date(
+        &mut entries,
+        entry("0", Some("2022-01-01T00:00:00Z"), "2022-01-01T00:00:00Z"),
+        DateOrder::PublishedThenUpdated,
+    );
+
+    assert_eq!(
+        vec!["d", "c", "b", "a", "0"],
+        entries.iter().map(|it| it.id()).collect::<Vec<&str>>(),
+    );
+
+    let mut entries = vec![
+        entry("a", Some("2023-01-01T00:00:00Z"), "2023-04-01T00:00:00Z"),
+        entry("c", Some("2023-03-01T00:00:00Z"), "2023-03-01T00:00:00Z"),
+    ];
+    insert_by_date(
+        &mut entries,
+        entry("b", Some("2023-05-01T00:00:00Z"), "2023-03-15T00:00:00Z"),
+        DateOrder::UpdatedOnly,
+    );
+    assert_eq!(
+        vec!["a", "b", "c"],
+        entries.iter().map(|it| it.id()).collect::<Vec<&str>>(),
+    );
+}
@@ -0,0 +1,180 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use argh::FromArgs;
+use atom_syndication::{Entry as AtomEntry, Feed};
+use log::warn;
+
+use crate::kaboom_command::KaboomCommand;
+use crate::kaboom_feed::{default_reject_path, KaboomFeed};
+use crate::Kaboom;
+
+#[derive(FromArgs, Debug)]
+/// Remove specific entries from the Atom feed, and by default send the deleted
+/// entries to the same reject file used by `kaboom prune`.
+#[argh(subcommand, name = "rm")]
+pub struct RmCommand {
+    #[argh(positional)]
+    /// the exact URI(s) of the entries to remove
+    ids: Vec<String>,
+
+    #[argh(option, short = 'g')]
+    /// remove all entries whose URI matches this glob, where * matches any
+    /// run of characters and ? matches exactly one. can be provided multiple
+    /// times.
+    glob: Vec<String>,
+
+    #[argh(option, short = 't')]
+    /// remove all entries whose title contains this text. can be provided
+    /// multiple times.
+    title: Vec<String>,
+
+    #[argh(switch, short = 'R')]
+    /// skip sending removed entries to the *reject_file*, described below
+    no_reject: bool,
+
+    #[argh(option, short = 'r')]
+    /// path to an Atom file to store removed entries for backup/archival
+    /// purposes. defaults to the same reject file as `kaboom prune`.
+    reject_file: Option<PathBuf>,
+}
+
+impl KaboomCommand for RmCommand {
+    fn run(&self, top_args: &Kaboom) -> Result<()> {
+        if self.ids.is_empty() && self.glob.is_empty() && self.title.is_empty() {
+            bail!("at least one id, glob, or title must be provided to select entries to remove");
+        }
+
+        let _lock = Feed::lock(&top_args.file, top_args.wait, top_args.no_op)?;
+        let mut feed = Feed::read_from_path(&top_args.file)?;
+        let original = feed.clone();
+
+        for id in &self.ids {
+            if !feed.entries().iter().any(|entry| entry.id() == id) {
+                warn!("no entry with id {} exists in the feed", id);
+            }
+        }
+
+        let (removed, kept): (Vec<AtomEntry>, Vec<AtomEntry>) = feed
+            .entries
+            .drain(..)
+            .partition(|entry| self.matches(entry));
+        feed.set_entries(kept);
+
+        if removed.is_empty() {
+            warn!("not removing anything because no entries matched");
+            return Ok(());
+        }
+
+        feed.stamp_if_modified(&original, top_args.feed_updated, !top_args.no_generator);
+
+        if self.no_reject {
+            warn!(
+                "not writing removed entries anywhere for backup because no-reject was requested"
+            );
+        } else {
+            feed.write_rejects_to_path(
+                removed,
+                &self
+                    .reject_file
+                    .clone()
+                    .unwrap_or_else(|| default_reject_path(&top_args.file)),
+                top_args.date_order,
+                top_args.no_op,
+            )?;
+        }
+
+        feed.write_or_preview(&original, &top_args.file, top_args.no_op)?;
+
+        Ok(())
+    }
+}
+
+impl RmCommand {
+    fn matches(&self, entry: &AtomEntry) -> bool {
+        self.ids.iter().any(|id| entry.id() == id)
+            || self.glob.iter().any(|glob| glob_matches(glob, entry.id()))
+            || self
+                .title
+                .iter()
+                .any(|title| entry.title().as_str().contains(title.as_str()))
+    }
+}
+
+/// A deliberately tiny glob implementation: * matches any (possibly empty) run
+/// of characters, ? matches exactly one character, and everything else matches
+/// only itself. There's no escaping, character classes, or path semantics,
+/// since entry IDs are URIs rather than paths.
+pub fn glob_matches(glob: &str, candidate: &str) -> bool {
+    let glob: Vec<char> = glob.chars().collect();
+    let candidate: Vec<char> = candidate.chars().collect();
+    let (mut gidx, mut cidx) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while cidx < candidate.len() {
+        match glob.get(gidx) {
+            Some('*') => {
+                backtrack = Some((gidx, cidx));
+                gidx += 1;
+            }
+            Some('?') => {
+                gidx += 1;
+                cidx += 1;
+            }
+            Some(ch) if *ch == candidate[cidx] => {
+                gidx += 1;
+                cidx += 1;
+            }
+            _ => match backtrack {
+                // Let the most recent star swallow one more character, and
+                // try again from just after it.
+                Some((star_gidx, star_cidx)) => {
+                    backtrack = Some((star_gidx, star_cidx + 1));
+                    gidx = star_gidx + 1;
+                    cidx = star_cidx + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    glob[gidx..].iter().all(|ch| *ch == '*')
+}
+
+#[test]
+fn glob_matches_behavior() {
+    assert!(glob_matches(
+        "https://example.com/001-foobar.html",
+        "https://example.com/001-foobar.html"
+    ));
+    assert!(glob_matches(
+        "https://example.com/*",
+        "https://example.com/001-foobar.html"
+    ));
+    assert!(glob_matches(
+        "*/00?-*.html",
+        "https://example.com/001-foobar.html"
+    ));
+    assert!(glob_matches("*", ""));
+    assert!(!glob_matches(
+        "https://example.com/*.xml",
+        "https://example.com/001-foobar.html"
+    ));
+    assert!(!glob_matches(
+        "https://example.com/00?",
+        "https://example.com/0"
+    ));
+}
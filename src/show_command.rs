@@ -0,0 +1,49 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use anyhow::{anyhow, Result};
+use argh::FromArgs;
+use atom_syndication::Feed;
+
+use crate::json::OutputFormat;
+use crate::kaboom_command::KaboomCommand;
+use crate::kaboom_entry::KaboomEntry;
+use crate::kaboom_feed::KaboomFeed;
+use crate::Kaboom;
+
+#[derive(FromArgs, Debug)]
+/// Display everything known about a single entry, including its content.
+#[argh(subcommand, name = "show")]
+pub struct ShowCommand {
+    #[argh(positional)]
+    /// the URI of the entry to show
+    id: String,
+}
+
+impl KaboomCommand for ShowCommand {
+    fn run(&self, top_args: &Kaboom) -> Result<()> {
+        let feed = Feed::read_from_path(&top_args.file)?;
+        let entry = feed
+            .entries()
+            .iter()
+            .find(|entry| entry.id() == self.id)
+            .ok_or_else(|| anyhow!("no entry with id {} exists in the feed", self.id))?;
+
+        match top_args.format {
+            OutputFormat::Human => println!("{}", entry.as_human_text()),
+            OutputFormat::Json | OutputFormat::JsonLines => println!("{}", entry.as_json()),
+        }
+
+        Ok(())
+    }
+}
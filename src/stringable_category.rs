@@ -0,0 +1,134 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use atom_syndication::Category;
+
+use crate::json::JsonValue;
+use crate::stringable_link::split_suffixes;
+
+/// A category (tag) that can round-trip through the same bracket-suffix syntax
+/// as [crate::stringable_link::StringableLink]: "term[scheme=URI][label=Label]",
+/// where both suffixes are optional.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StringableCategory {
+    pub category_form: Category,
+    pub string_form: String,
+}
+
+impl StringableCategory {
+    /// The structured form of this category, for machine-readable output.
+    pub fn as_json(&self) -> JsonValue {
+        let category = &self.category_form;
+
+        JsonValue::Object(vec![
+            ("term", category.term().into()),
+            ("scheme", category.scheme().into()),
+            ("label", category.label().into()),
+        ])
+    }
+}
+
+impl Display for StringableCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.string_form)
+    }
+}
+
+impl From<&Category> for StringableCategory {
+    fn from(it: &Category) -> Self {
+        Self {
+            category_form: it.clone(),
+            string_form: category_to_string(it),
+        }
+    }
+}
+
+impl FromStr for StringableCategory {
+    type Err = &'static str;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        let category = string_to_category(it);
+
+        if category.term().trim().is_empty() {
+            return Err("category term cannot be empty");
+        }
+
+        Ok(Self {
+            category_form: category,
+            string_form: String::from(it),
+        })
+    }
+}
+
+impl From<StringableCategory> for Category {
+    fn from(it: StringableCategory) -> Category {
+        it.category_form
+    }
+}
+
+fn category_to_string(it: &Category) -> String {
+    format!(
+        "{}{}{}",
+        it.term(),
+        it.scheme()
+            .map_or("".to_string(), |scheme| format!("[scheme={}]", scheme)),
+        it.label()
+            .map_or("".to_string(), |label| format!("[label={}]", label)),
+    )
+}
+
+fn string_to_category(it: &str) -> Category {
+    let mut category = Category::default();
+
+    let (term, suffixes) = split_suffixes(it, &["scheme", "label"]);
+    for (key, val) in suffixes {
+        match key {
+            "scheme" => category.set_scheme(val.to_string()),
+            "label" => category.set_label(val.to_string()),
+            _ => unreachable!("split_suffixes only returns known keys"),
+        }
+    }
+    category.set_term(term);
+
+    category
+}
+
+#[test]
+fn string_to_category_behavior() {
+    let category = Category {
+        term: "rust".into(),
+        scheme: Some("https://example.com/tags".into()),
+        label: Some("Rust (the language)".into()),
+    };
+
+    assert_eq!(
+        category,
+        string_to_category("rust[scheme=https://example.com/tags][label=Rust (the language)]"),
+    );
+    assert_eq!(
+        "rust[scheme=https://example.com/tags][label=Rust (the language)]",
+        category_to_string(&category),
+    );
+
+    assert_eq!(
+        Category {
+            term: "[weird]".into(),
+            ..Default::default()
+        },
+        string_to_category("[weird]"),
+    );
+    assert!("[label=Nothing]".parse::<StringableCategory>().is_err());
+}
@@ -17,12 +17,33 @@ use std::str::FromStr;
 use atom_syndication::Link as AtomLink;
 use log::debug;
 
+use crate::json::JsonValue;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct StringableLink {
     pub link_form: AtomLink,
     pub string_form: String,
 }
 
+impl StringableLink {
+    /// The structured form of this link, for machine-readable output.
+    pub fn as_json(&self) -> JsonValue {
+        let link = &self.link_form;
+
+        JsonValue::Object(vec![
+            ("href", link.href().into()),
+            ("rel", link.rel().into()),
+            ("type", link.mime_type().into()),
+            ("hreflang", link.hreflang().into()),
+            ("title", link.title().into()),
+            (
+                "length",
+                link.length().and_then(|l| l.parse::<u64>().ok()).into(),
+            ),
+        ])
+    }
+}
+
 impl Display for StringableLink {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.string_form)
@@ -51,7 +72,15 @@ impl FromStr for StringableLink {
     type Err = &'static str;
 
     fn from_str(it: &str) -> Result<Self, Self::Err> {
-        Ok(StringableLink::from(it))
+        let link = StringableLink::from(it);
+
+        if let Some(length) = link.link_form.length() {
+            if length.parse::<u64>().is_err() {
+                return Err("link length must be a number of bytes");
+            }
+        }
+
+        Ok(link)
     }
 }
 
@@ -69,7 +98,7 @@ impl From<StringableLink> for String {
 
 fn link_to_string(it: &AtomLink) -> String {
     format!(
-        "{}{}{}{}{}",
+        "{}{}{}{}{}{}",
         it.href(),
         {
             let rel = it.rel();
@@ -85,6 +114,8 @@ fn link_to_string(it: &AtomLink) -> String {
             .map_or("".to_string(), |hl| format!("[lang={}]", hl)),
         it.title()
             .map_or("".to_string(), |title| format!("[title={}]", title)),
+        it.length()
+            .map_or("".to_string(), |length| format!("[length={}]", length)),
     )
 }
 
@@ -163,7 +194,6 @@ fn link_to_string_behavior() {
 
 fn string_to_link(it: &str) -> AtomLink {
     let mut link = AtomLink::default();
-    let mut rem_input = String::from(it);
 
     // Some feeds never set a rel, and the default of the atom_syndication
     // crate is "alternate", which I don't entirely agree with: what I see in the wild
@@ -171,67 +201,116 @@ fn string_to_link(it: &str) -> AtomLink {
     // the site that the feed describes: which is link=related.
     link.set_rel("related");
 
+    let (href, suffixes) = split_suffixes(it, &["rel", "type", "title", "lang", "length"]);
+    for (key, val) in suffixes {
+        match key {
+            "rel" => link.set_rel(val),
+            "type" => link.set_mime_type(val.to_string()),
+            "title" => link.set_title(val.to_string()),
+            "lang" => link.set_hreflang(val.to_string()),
+            "length" => link.set_length(val.to_string()),
+            _ => unreachable!("split_suffixes only returns known keys"),
+        }
+    }
+    link.set_href(href);
+
+    link
+}
+
+/// Add *new* to *links*, modifying in place any existing link with the same
+/// href rather than adding a second one. Returns whether anything changed.
+pub fn merge_links(links: &mut Vec<AtomLink>, new: &[StringableLink]) -> bool {
+    let mut any_updates = false;
+
+    for rel_link in new {
+        let rl = &rel_link.link_form;
+        if let Some(existing) = links.iter_mut().find(|link| link.href() == rl.href()) {
+            let same = existing == rl;
+            debug!(
+                "link {} aleady exists, {}",
+                &rel_link.string_form,
+                if same {
+                    "seems to be equivalent, skipping!"
+                } else {
+                    "modifying in place"
+                }
+            );
+
+            if !same {
+                existing.set_rel(rl.rel());
+                existing.set_hreflang(rl.hreflang.clone());
+                existing.set_mime_type(rl.mime_type.clone());
+                existing.set_title(rl.title.clone());
+                existing.set_length(rl.length.clone());
+                any_updates = true;
+            }
+        } else {
+            links.push(rl.clone());
+            any_updates = true;
+        }
+    }
+
+    any_updates
+}
+
+/// Peel any trailing [key=value] suffixes off of *it*, where key is one of
+/// *known*, returning what's left along with the suffixes, last first. The
+/// first suffix that isn't one of ours ends the search, since it's presumably
+/// part of the value itself (URLs can contain brackets and equals signs too).
+pub fn split_suffixes<'a>(it: &'a str, known: &[&str]) -> (&'a str, Vec<(&'a str, &'a str)>) {
+    let mut rem_input = it;
+    let mut suffixes = Vec::new();
+
     loop {
         // If the string repr doesn't end with a bracket, we're assuming the
-        // remainder is a verbatim URL, and don't care what it contains.
+        // remainder is a verbatim value, and don't care what it contains.
         if !rem_input.ends_with(']') {
-            debug!("no rbracket: {}", &rem_input);
-            link.set_href(rem_input);
+            debug!("no rbracket: {}", rem_input);
             break;
         }
 
-        if let Some(lidx) = rem_input.rfind('[') {
-            let eidx = match rem_input.rfind('=') {
-                Some(candidate) => {
-                    if candidate < lidx {
-                        // While we found an opening bracket, it came *after*
-                        // the last equals sign, and so this [] pair definitely
-                        // doesn't refer to an instruction we can parse. Bye!
-                        debug!("= before [: {}", &rem_input);
-                        link.set_href(rem_input);
-                        break;
-                    }
-                    candidate
-                }
-                None => {
-                    // No equals sign found, so this [] pair is part of the URL,
-                    // not an instruction. Adios!
-                    debug!("no equals: {}", &rem_input);
-                    link.set_href(rem_input);
-                    break;
-                }
-            };
-
-            // We now have *at least* a [=] blob, which isn't a valid
-            // instruction, but is at least something we can start validity
-            // checking.
-            let rem_len = rem_input.len();
-            match rem_input[lidx + 1..rem_len - 1].split_at(eidx - lidx) {
-                ("rel=", rel) => link.set_rel(rel),
-                ("type=", mime) => link.set_mime_type(mime.to_string()),
-                ("title=", title) => link.set_title(title.to_string()),
-                ("lang=", lang) => link.set_hreflang(lang.to_string()),
-                (key, val) => {
-                    // Tag not recognized as anything we can parse, so assume
-                    // it's a trailing part of the URL instead.
-                    debug!("unparseable instruction: key={} val={}", key, val);
-                    link.set_href(rem_input);
-                    break;
-                }
+        let lidx = match rem_input.rfind('[') {
+            Some(lidx) => lidx,
+            None => {
+                // Hm, we have a trailing bracket, but it was never opened.
+                // Presumably this is a part of the value, too. See ya!
+                break;
             }
-
-            // We're completely done with this instruction, remove it from our
-            // ever-destructing string, and proceed to the next loop iteration.
-            rem_input.truncate(lidx);
-        } else {
-            // Hm, we have a trailing bracket, but it was never opened.
-            // Presumably this is a part of the URL, too. See ya!
-            link.set_href(rem_input);
+        };
+
+        let eidx = match rem_input.rfind('=') {
+            Some(candidate) if candidate > lidx => candidate,
+            Some(_) => {
+                // While we found an opening bracket, it came *after* the last
+                // equals sign, and so this [] pair definitely doesn't refer to
+                // an instruction we can parse. Bye!
+                debug!("= before [: {}", rem_input);
+                break;
+            }
+            None => {
+                // No equals sign found, so this [] pair is part of the value,
+                // not an instruction. Adios!
+                debug!("no equals: {}", rem_input);
+                break;
+            }
+        };
+
+        let key = &rem_input[lidx + 1..eidx];
+        let val = &rem_input[eidx + 1..rem_input.len() - 1];
+        if !known.contains(&key) {
+            // Tag not recognized as anything we can parse, so assume it's a
+            // trailing part of the value instead.
+            debug!("unparseable instruction: key={} val={}", key, val);
             break;
         }
+
+        // We're completely done with this instruction, remove it from our
+        // ever-destructing string, and proceed to the next loop iteration.
+        suffixes.push((key, val));
+        rem_input = &rem_input[..lidx];
     }
 
-    link
+    (rem_input, suffixes)
 }
 
 #[test]
@@ -338,4 +417,25 @@ fn string_to_link_behavior() {
     };
 
     assert_eq!(link8, string_to_link("https://example.com/feed.xml[]"),);
+
+    let link9 = AtomLink {
+        href: "https://example.com/001.mp3".into(),
+        rel: "enclosure".into(),
+        hreflang: None,
+        mime_type: Some("audio/mpeg".into()),
+        title: None,
+        length: Some("12345".into()),
+    };
+
+    assert_eq!(
+        link9,
+        string_to_link("https://example.com/001.mp3[rel=enclosure][type=audio/mpeg][length=12345]"),
+    );
+    assert_eq!(
+        "https://example.com/001.mp3[rel=enclosure][type=audio/mpeg][length=12345]",
+        link_to_string(&link9),
+    );
+    assert!("https://example.com/001.mp3[length=big]"
+        .parse::<StringableLink>()
+        .is_err());
 }
@@ -0,0 +1,196 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use std::fmt::Display;
+use std::str::FromStr;
+
+use atom_syndication::Person;
+
+use crate::json::JsonValue;
+
+/// A person (author or contributor) that can round-trip through the common
+/// "Name <email> (uri)" syntax, where both the email and the uri are optional.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StringablePerson {
+    pub person_form: Person,
+    pub string_form: String,
+}
+
+impl StringablePerson {
+    /// The structured form of this person, for machine-readable output.
+    pub fn as_json(&self) -> JsonValue {
+        let person = &self.person_form;
+
+        JsonValue::Object(vec![
+            ("name", person.name().into()),
+            ("email", person.email().into()),
+            ("uri", person.uri().into()),
+        ])
+    }
+}
+
+impl Display for StringablePerson {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.string_form)
+    }
+}
+
+impl From<&Person> for StringablePerson {
+    fn from(it: &Person) -> Self {
+        Self {
+            person_form: it.clone(),
+            string_form: person_to_string(it),
+        }
+    }
+}
+
+impl FromStr for StringablePerson {
+    type Err = &'static str;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            person_form: string_to_person(it)?,
+            string_form: String::from(it),
+        })
+    }
+}
+
+impl From<StringablePerson> for Person {
+    fn from(it: StringablePerson) -> Person {
+        it.person_form
+    }
+}
+
+fn person_to_string(it: &Person) -> String {
+    format!(
+        "{}{}{}",
+        it.name(),
+        it.email()
+            .map_or("".into(), |email| format!(" <{}>", email)),
+        it.uri().map_or("".into(), |uri| format!(" ({})", uri)),
+    )
+}
+
+#[test]
+fn person_to_string_behavior() {
+    let person1 = Person {
+        name: "klardotsh".into(),
+        email: Some("klardotsh@example.com".into()),
+        uri: Some("https://klar.sh".into()),
+    };
+
+    assert_eq!(
+        "klardotsh <klardotsh@example.com> (https://klar.sh)",
+        person_to_string(&person1),
+    );
+
+    let person2 = Person {
+        name: "Josh Klar".into(),
+        email: None,
+        uri: Some("https://klar.sh".into()),
+    };
+
+    assert_eq!("Josh Klar (https://klar.sh)", person_to_string(&person2));
+
+    let person3 = Person {
+        name: "Josh Klar".into(),
+        email: None,
+        uri: None,
+    };
+
+    assert_eq!("Josh Klar", person_to_string(&person3));
+}
+
+fn string_to_person(it: &str) -> Result<Person, &'static str> {
+    let mut rem_input = it.trim();
+    let mut person = Person::default();
+
+    // Work backwards, just like links do: first the (uri), then the <email>,
+    // and whatever's left over is the name.
+    if rem_input.ends_with(')') {
+        let lidx = rem_input
+            .rfind('(')
+            .ok_or("unbalanced parentheses, expected Name <email> (uri)")?;
+        let uri = rem_input[lidx + 1..rem_input.len() - 1].trim();
+
+        if !uri.contains(':') {
+            return Err("uri in parentheses must be absolute, like https://example.com");
+        }
+
+        person.set_uri(uri.to_string());
+        rem_input = rem_input[..lidx].trim_end();
+    }
+
+    if rem_input.ends_with('>') {
+        let lidx = rem_input
+            .rfind('<')
+            .ok_or("unbalanced angle brackets, expected Name <email> (uri)")?;
+        let email = rem_input[lidx + 1..rem_input.len() - 1].trim();
+
+        if !email.contains('@') || email.contains(char::is_whitespace) {
+            return Err("email in angle brackets must look like someone@example.com");
+        }
+
+        person.set_email(email.to_string());
+        rem_input = rem_input[..lidx].trim_end();
+    }
+
+    if rem_input.is_empty() {
+        return Err("a person must have a name, expected Name <email> (uri)");
+    }
+
+    if rem_input.contains(['<', '>', '(', ')']) {
+        return Err("unexpected text after the name, expected Name <email> (uri)");
+    }
+
+    person.set_name(rem_input);
+
+    Ok(person)
+}
+
+#[test]
+fn string_to_person_behavior() {
+    assert_eq!(
+        Ok(Person {
+            name: "klardotsh".into(),
+            email: Some("klardotsh@example.com".into()),
+            uri: Some("https://klar.sh".into()),
+        }),
+        string_to_person("klardotsh <klardotsh@example.com> (https://klar.sh)"),
+    );
+
+    assert_eq!(
+        Ok(Person {
+            name: "Josh Klar".into(),
+            email: Some("klardotsh@example.com".into()),
+            uri: None,
+        }),
+        string_to_person("  Josh Klar<klardotsh@example.com>"),
+    );
+
+    assert_eq!(
+        Ok(Person {
+            name: "Josh Klar".into(),
+            email: None,
+            uri: Some("https://klar.sh".into()),
+        }),
+        string_to_person("Josh Klar (https://klar.sh)"),
+    );
+
+    assert!(string_to_person("").is_err());
+    assert!(string_to_person("<klardotsh@example.com>").is_err());
+    assert!(string_to_person("klardotsh <klardotsh.example.com>").is_err());
+    assert!(string_to_person("klardotsh klardotsh@example.com>").is_err());
+    assert!(string_to_person("klardotsh (klar.sh)").is_err());
+    assert!(string_to_person("klardotsh (https://klar.sh) <klardotsh@example.com>").is_err());
+}
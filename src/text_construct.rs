@@ -0,0 +1,253 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+//! Atom text constructs (RFC 4287 section 3.1), such as titles and summaries,
+//! built from command-line arguments.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use atom_syndication::{Text, TextType};
+use log::warn;
+use quick_xml::events::Event;
+
+/// The type of a text construct, as given on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextKind {
+    Text,
+    Html,
+    Xhtml,
+}
+
+impl Default for TextKind {
+    fn default() -> Self {
+        Self::Text
+    }
+}
+
+impl FromStr for TextKind {
+    type Err = &'static str;
+
+    fn from_str(it: &str) -> Result<Self, Self::Err> {
+        match it {
+            "text" => Ok(Self::Text),
+            "html" => Ok(Self::Html),
+            "xhtml" => Ok(Self::Xhtml),
+            _ => Err("expected text, html, or xhtml"),
+        }
+    }
+}
+
+impl TextKind {
+    /// Build a text construct of this kind holding *value*, where *name* says
+    /// what it's for (title, summary, and so on) in case of errors. xhtml must
+    /// be well-formed, and is wrapped in a div if it isn't already.
+    pub fn text(self, name: &str, value: &str) -> Result<Text> {
+        Ok(match self {
+            Self::Text => Text::plain(value),
+            Self::Html => Text::html(value),
+            Self::Xhtml => Text::xhtml(
+                xhtml_div(value)
+                    .with_context(|| format!("xhtml {} is not well-formed XML", name))?,
+            ),
+        })
+    }
+}
+
+/// The name of *text*'s type, as given on the command line and in output.
+pub fn text_type_name(text: &Text) -> &'static str {
+    match text.r#type {
+        TextType::Text => "text",
+        TextType::Html => "html",
+        TextType::Xhtml => "xhtml",
+    }
+}
+
+/// *text*'s type as a human-readable key=value line, or nothing for plain
+/// text, since that's what a text construct is unless it says otherwise.
+pub fn text_type_line(key: &str, text: &Text) -> Option<String> {
+    match text.r#type {
+        TextType::Text => None,
+        _ => Some(format!("{}_type={}", key, text_type_name(text))),
+    }
+}
+
+/// Work out the new value of the text construct *name*, currently *existing*,
+/// given a new *value* and/or *kind* from the command line. A new value
+/// without a kind is plain text, and a new kind without a value re-types the
+/// existing text. Returns None if there's nothing to change.
+pub fn changed_text(
+    name: &str,
+    existing: Option<&Text>,
+    value: Option<&str>,
+    kind: Option<TextKind>,
+) -> Result<Option<Text>> {
+    let text = match (value, kind, existing) {
+        (Some(value), kind, _) => kind.unwrap_or_default().text(name, value)?,
+        (None, Some(kind), Some(existing)) if existing.r#type == TextType::Xhtml => {
+            kind.text(name, xhtml_div_contents(existing.as_str()))?
+        }
+        (None, Some(kind), Some(existing)) => kind.text(name, existing.as_str())?,
+        (None, Some(_), None) => {
+            warn!("ignoring the {} type because there is no {}", name, name);
+            return Ok(None);
+        }
+        (None, None, _) => return Ok(None),
+    };
+
+    match existing {
+        Some(existing) if existing.as_str() == text.as_str() && existing.r#type == text.r#type => {
+            Ok(None)
+        }
+        _ => Ok(Some(text)),
+    }
+}
+
+#[test]
+fn changed_text_behavior() {
+    let html = Text::html("<em>hi</em>");
+
+    assert_eq!(
+        None,
+        changed_text("title", Some(&html), None, None).unwrap()
+    );
+    assert_eq!(
+        None,
+        changed_text("title", Some(&html), None, Some(TextKind::Html)).unwrap()
+    );
+    assert_eq!(
+        Some(Text::plain("<em>hi</em>")),
+        changed_text("title", Some(&html), Some("<em>hi</em>"), None).unwrap()
+    );
+    assert_eq!(
+        Some(Text::xhtml(
+            "<div xmlns=\"http://www.w3.org/1999/xhtml\"><em>hi</em></div>"
+        )),
+        changed_text("title", Some(&html), None, Some(TextKind::Xhtml)).unwrap()
+    );
+    assert_eq!(
+        Some(Text::html("<em>hi</em>")),
+        changed_text(
+            "title",
+            Some(&Text::xhtml(
+                "<div xmlns=\"http://www.w3.org/1999/xhtml\"><em>hi</em></div>"
+            )),
+            None,
+            Some(TextKind::Html)
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        None,
+        changed_text("rights", None, None, Some(TextKind::Html)).unwrap()
+    );
+    assert!(changed_text("title", None, Some("<em>hi"), Some(TextKind::Xhtml)).is_err());
+}
+
+pub const XHTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+
+/// Wrap *value* in the xhtml div that RFC 4287 requires of xhtml text
+/// constructs and content (unless it's already there), making sure the result
+/// is well-formed.
+pub fn xhtml_div(value: &str) -> Result<String> {
+    let wrapped = match xml_root(value.trim()) {
+        Ok((name, xmlns)) if name == "div" && xmlns.as_deref() == Some(XHTML_NAMESPACE) => {
+            value.trim().to_string()
+        }
+        _ => format!("<div xmlns=\"{}\">{}</div>", XHTML_NAMESPACE, value),
+    };
+
+    xml_root(&wrapped)?;
+    Ok(wrapped)
+}
+
+/// The reverse of [xhtml_div], for xhtml that is being turned into some other
+/// kind of text.
+fn xhtml_div_contents(xhtml: &str) -> &str {
+    let opening = format!("<div xmlns=\"{}\">", XHTML_NAMESPACE);
+
+    xhtml
+        .trim()
+        .strip_prefix(opening.as_str())
+        .and_then(|it| it.strip_suffix("</div>"))
+        .unwrap_or(xhtml)
+}
+
+/// Check that *xml* is a single well-formed XML element, returning its name
+/// and xmlns attribute, if any.
+fn xml_root(xml: &str) -> Result<(String, Option<String>)> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut root: Option<(String, Option<String>)> = None;
+    let mut depth = 0;
+
+    loop {
+        let event = reader.read_event()?;
+        let at_top = depth == 0;
+
+        match event {
+            Event::Start(ref tag) | Event::Empty(ref tag) => {
+                if at_top {
+                    if root.is_some() {
+                        bail!("more than one top-level element");
+                    }
+
+                    root = Some((
+                        String::from_utf8_lossy(tag.name().as_ref()).into_owned(),
+                        tag.try_get_attribute("xmlns")?
+                            .map(|it| {
+                                it.decode_and_unescape_value(&reader)
+                                    .map(|it| it.into_owned())
+                            })
+                            .transpose()?,
+                    ));
+                }
+
+                if let Event::Start(_) = event {
+                    depth += 1;
+                }
+            }
+            Event::End(_) => depth -= 1,
+            Event::Text(text) => {
+                let text = text.unescape()?;
+                if at_top && !text.trim().is_empty() {
+                    bail!("text outside of any element");
+                }
+            }
+            Event::CData(_) if at_top => bail!("text outside of any element"),
+            Event::Eof if depth > 0 => bail!("unclosed element"),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    root.ok_or_else(|| anyhow!("no element found"))
+}
+
+#[test]
+fn xhtml_div_behavior() {
+    assert_eq!(
+        "<div xmlns=\"http://www.w3.org/1999/xhtml\"><p>hi &amp; bye</p></div>",
+        xhtml_div("<p>hi &amp; bye</p>").unwrap()
+    );
+    assert_eq!(
+        "<div xmlns=\"http://www.w3.org/1999/xhtml\">hi</div>",
+        xhtml_div(" <div xmlns=\"http://www.w3.org/1999/xhtml\">hi</div>\n").unwrap()
+    );
+    assert_eq!(
+        "<div xmlns=\"http://www.w3.org/1999/xhtml\"><div>a</div></div>",
+        xhtml_div("<div>a</div>").unwrap()
+    );
+    assert!(xhtml_div("<p>unclosed").is_err());
+    assert!(xhtml_div("<p>mismatched</b>").is_err());
+    assert!(xhtml_div("<p>bad &nbsp; entity</p>").is_err());
+}
@@ -0,0 +1,460 @@
+// Copyright (C) 2023 Josh Klar aka "klardotsh" <josh@klar.sh>
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES WITH
+// REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF MERCHANTABILITY AND
+// FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR ANY SPECIAL, DIRECT,
+// INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES WHATSOEVER RESULTING FROM
+// LOSS OF USE, DATA OR PROFITS, WHETHER IN AN ACTION OF CONTRACT, NEGLIGENCE OR
+// OTHER TORTIOUS ACTION, ARISING OUT OF OR IN CONNECTION WITH THE USE OR
+// PERFORMANCE OF THIS SOFTWARE.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use anyhow::{bail, Result};
+use argh::FromArgs;
+use atom_syndication::{Entry as AtomEntry, Feed, Link as AtomLink};
+
+use crate::content_type::{is_base64, is_base64_type, is_mime_type};
+use crate::json::{JsonValue, OutputFormat};
+use crate::kaboom_command::KaboomCommand;
+use crate::kaboom_feed::KaboomFeed;
+use crate::Kaboom;
+
+#[derive(Clone, Copy, Eq, Debug, PartialEq)]
+pub enum Severity {
+    /// The feed violates a MUST (or MUST NOT) of RFC 4287
+    Error,
+    /// The feed violates a SHOULD (or SHOULD NOT), or is otherwise likely to
+    /// confuse feed readers
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub severity: Severity,
+    /// Either "feed", or the ID of the offending entry
+    pub subject: String,
+    pub message: String,
+}
+
+impl Violation {
+    fn error(subject: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            subject: subject.to_string(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(subject: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            subject: subject.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn as_human_line(&self) -> String {
+        format!(
+            "severity={}\tsubject={}\tmessage={}",
+            self.severity, self.subject, self.message
+        )
+    }
+
+    pub fn as_json(&self) -> JsonValue {
+        JsonValue::Object(vec![
+            ("severity", self.severity.to_string().into()),
+            ("subject", self.subject.as_str().into()),
+            ("message", self.message.as_str().into()),
+        ])
+    }
+}
+
+#[derive(FromArgs, Debug)]
+/// Check the feed against the requirements of RFC 4287, reporting every
+/// violation found. Exits non-zero if any errors (violations of a MUST) are
+/// found.
+#[argh(subcommand, name = "validate")]
+pub struct ValidateCommand {
+    #[argh(switch, short = 'W')]
+    /// also exit non-zero if any warnings (violations of a SHOULD) are found
+    warnings_as_errors: bool,
+}
+
+impl KaboomCommand for ValidateCommand {
+    fn run(&self, top_args: &Kaboom) -> Result<()> {
+        let feed = Feed::read_from_path(&top_args.file)?;
+        let violations = validate_feed(&feed);
+
+        match top_args.format {
+            OutputFormat::Human => {
+                for violation in &violations {
+                    println!("{}", violation.as_human_line());
+                }
+            }
+            OutputFormat::Json => println!(
+                "{}",
+                JsonValue::Array(violations.iter().map(Violation::as_json).collect())
+            ),
+            OutputFormat::JsonLines => {
+                for violation in &violations {
+                    println!("{}", violation.as_json());
+                }
+            }
+        }
+
+        let errors = violations
+            .iter()
+            .filter(|it| it.severity == Severity::Error)
+            .count();
+        let warnings = violations.len() - errors;
+
+        if errors > 0 || (self.warnings_as_errors && warnings > 0) {
+            bail!(
+                "feed is not valid: {} error(s), {} warning(s)",
+                errors,
+                warnings
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Run every check we know of against *feed*, returning violations in the
+/// order they appear in the document.
+pub fn validate_feed(feed: &Feed) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    if feed.id().trim().is_empty() {
+        violations.push(Violation::error("feed", "feed has no id"));
+    } else if !is_iri(feed.id()) {
+        violations.push(Violation::error(
+            "feed",
+            format!("feed id {} is not an absolute IRI", feed.id()),
+        ));
+    }
+
+    if feed.title().as_str().trim().is_empty() {
+        violations.push(Violation::error("feed", "feed has no title"));
+    }
+
+    if !feed.links().iter().any(|link| link.rel() == "self") {
+        violations.push(Violation::warning(
+            "feed",
+            "feed has no rel=self link pointing to its own URL",
+        ));
+    }
+
+    validate_links("feed", feed.links(), &mut violations);
+
+    let mut seen_ids: HashMap<&str, usize> = HashMap::new();
+
+    for entry in feed.entries() {
+        let count = seen_ids.entry(entry.id()).or_insert(0);
+        *count += 1;
+
+        if *count == 2 {
+            violations.push(Violation::error(
+                entry.id(),
+                "more than one entry has this id",
+            ));
+        }
+
+        validate_entry(entry, !feed.authors().is_empty(), &mut violations);
+    }
+
+    violations
+}
+
+fn validate_entry(entry: &AtomEntry, feed_has_authors: bool, violations: &mut Vec<Violation>) {
+    let subject = entry.id();
+
+    if entry.id().trim().is_empty() {
+        violations.push(Violation::error(subject, "entry has no id"));
+    } else if !is_iri(entry.id()) {
+        violations.push(Violation::error(subject, "entry id is not an absolute IRI"));
+    }
+
+    if entry.title().as_str().trim().is_empty() {
+        violations.push(Violation::error(subject, "entry has no title"));
+    }
+
+    if entry.authors().is_empty() && !feed_has_authors {
+        violations.push(Violation::error(
+            subject,
+            "entry has no author, and the feed has none to inherit",
+        ));
+    }
+
+    validate_links(subject, entry.links(), violations);
+
+    match entry.content() {
+        None => {
+            if !entry.links().iter().any(|link| link.rel() == "alternate") {
+                violations.push(Violation::error(
+                    subject,
+                    "entry has neither content nor a rel=alternate link",
+                ));
+            }
+        }
+        Some(content) => {
+            let content_type = content.content_type().unwrap_or("text");
+            let known_type = matches!(content_type, "text" | "html" | "xhtml");
+
+            if !known_type && !is_mime_type(content_type) {
+                violations.push(Violation::error(
+                    subject,
+                    format!(
+                        "content type {} is not text, html, xhtml, or a MIME type",
+                        content_type
+                    ),
+                ));
+            }
+
+            if let Some(src) = content.src() {
+                if content.value().map_or(false, |v| !v.is_empty()) {
+                    violations.push(Violation::error(
+                        subject,
+                        "content has both a src and a value, but content with a src must be empty",
+                    ));
+                }
+
+                if !is_iri(src) {
+                    violations.push(Violation::error(
+                        subject,
+                        format!("content src {} is not an absolute IRI", src),
+                    ));
+                }
+
+                match content.content_type() {
+                    None => violations.push(Violation::warning(
+                        subject,
+                        "content with a src has no type, but should have a MIME type",
+                    )),
+                    Some(_) if known_type => violations.push(Violation::error(
+                        subject,
+                        "content with a src must have a MIME type rather than text, html, or xhtml",
+                    )),
+                    Some(_) => {}
+                }
+            }
+
+            if content.src().is_none()
+                && is_base64_type(content_type)
+                && !content.value().map_or(true, is_base64)
+            {
+                violations.push(Violation::error(
+                    subject,
+                    format!("content of type {} is not valid base64", content_type),
+                ));
+            }
+
+            let needs_summary = content.src().is_some() || is_base64_type(content_type);
+
+            if needs_summary && entry.summary().is_none() {
+                violations.push(Violation::error(
+                    subject,
+                    "entry has out-of-line or binary content, and so must have a summary",
+                ));
+            }
+        }
+    }
+}
+
+fn validate_links(subject: &str, links: &[AtomLink], violations: &mut Vec<Violation>) {
+    let mut alternates: HashMap<(Option<&str>, Option<&str>), usize> = HashMap::new();
+
+    for link in links {
+        if link.href().trim().is_empty() {
+            violations.push(Violation::error(subject, "link has no href"));
+        }
+
+        match link.length() {
+            Some(length) if length.parse::<u64>().is_err() => violations.push(Violation::error(
+                subject,
+                format!("link length {} is not a number of bytes", length),
+            )),
+            None if link.rel() == "enclosure" => violations.push(Violation::warning(
+                subject,
+                "rel=enclosure link has no length",
+            )),
+            _ => {}
+        }
+
+        if link.rel() == "alternate" {
+            let count = alternates
+                .entry((link.mime_type(), link.hreflang()))
+                .or_insert(0);
+            *count += 1;
+
+            if *count == 2 {
+                violations.push(Violation::error(
+                    subject,
+                    "more than one rel=alternate link has the same type and lang",
+                ));
+            }
+        }
+    }
+}
+
+/// Loosely check whether *it* is an absolute IRI: a scheme (a letter followed
+/// by letters, digits, +, -, or .), a colon, and no whitespace anywhere.
+pub fn is_iri(it: &str) -> bool {
+    match it.split_once(':') {
+        Some((scheme, rest)) => {
+            scheme.starts_with(|ch: char| ch.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || "+-.".contains(ch))
+                && !rest.is_empty()
+                && !it.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+#[test]
+fn is_iri_behavior() {
+    assert!(is_iri("https://example.com/001-foobar.html"));
+    assert!(is_iri("urn:uuid:60a76c80-d399-11d9-b93C-0003939e0af6"));
+    assert!(is_iri("tag:example.com,2023:001"));
+    assert!(!is_iri("example.com/001-foobar.html"));
+    assert!(!is_iri("/001-foobar.html"));
+    assert!(!is_iri("https://example.com/001 foobar.html"));
+    assert!(!is_iri("1http://example.com"));
+}
+
+#[test]
+fn validate_feed_behavior() {
+    use atom_syndication::{Content, Person};
+
+    let mut feed = Feed {
+        title: "A feed".into(),
+        id: "https://example.com/feed.xml".into(),
+        ..Default::default()
+    };
+    feed.links.push(AtomLink {
+        href: "https://example.com/feed.xml".into(),
+        rel: "self".into(),
+        ..Default::default()
+    });
+    feed.authors.push(Person {
+        name: "klardotsh".into(),
+        ..Default::default()
+    });
+
+    let mut entry = AtomEntry {
+        id: "https://example.com/001-foobar.html".into(),
+        title: "001: Foobar".into(),
+        content: Some(Content {
+            value: Some("hi".into()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    feed.entries.push(entry.clone());
+
+    assert_eq!(Vec::<Violation>::new(), validate_feed(&feed));
+
+    entry.content = Some(Content {
+        value: Some("hi".into()),
+        src: Some("https://example.com/001-foobar.html".into()),
+        content_type: Some("html".into()),
+        ..Default::default()
+    });
+    entry.links.push(AtomLink {
+        href: "https://example.com/001-foobar.mp3".into(),
+        rel: "enclosure".into(),
+        ..Default::default()
+    });
+    feed.entries.push(entry);
+    feed.authors.clear();
+    feed.links.clear();
+
+    let found: Vec<(Severity, String)> = validate_feed(&feed)
+        .into_iter()
+        .map(|it| (it.severity, it.message))
+        .collect();
+
+    assert_eq!(
+        vec![
+            (
+                Severity::Warning,
+                "feed has no rel=self link pointing to its own URL".to_string()
+            ),
+            (
+                Severity::Error,
+                "entry has no author, and the feed has none to inherit".to_string()
+            ),
+            (
+                Severity::Error,
+                "more than one entry has this id".to_string()
+            ),
+            (
+                Severity::Error,
+                "entry has no author, and the feed has none to inherit".to_string()
+            ),
+            (
+                Severity::Warning,
+                "rel=enclosure link has no length".to_string()
+            ),
+            (
+                Severity::Error,
+                "content has both a src and a value, but content with a src must be empty"
+                    .to_string()
+            ),
+            (
+                Severity::Error,
+                "content with a src must have a MIME type rather than text, html, or xhtml"
+                    .to_string()
+            ),
+            (
+                Severity::Error,
+                "entry has out-of-line or binary content, and so must have a summary".to_string()
+            ),
+        ],
+        found,
+    );
+
+    // RFC 4287 only says content with a src SHOULD have a type.
+    let untyped = AtomEntry {
+        id: "https://example.com/002-untyped.html".into(),
+        title: "002: Untyped".into(),
+        summary: Some("Untyped".into()),
+        authors: vec![Person {
+            name: "klardotsh".into(),
+            ..Default::default()
+        }],
+        content: Some(Content {
+            src: Some("https://example.com/002-untyped.html".into()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut violations = Vec::new();
+    validate_entry(&untyped, true, &mut violations);
+    assert_eq!(
+        vec![(
+            Severity::Warning,
+            "content with a src has no type, but should have a MIME type".to_string()
+        )],
+        violations
+            .into_iter()
+            .map(|it| (it.severity, it.message))
+            .collect::<Vec<_>>(),
+    );
+}